@@ -0,0 +1,47 @@
+// Times the KEY1 Blowfish core on its own and through a full level 3 key setup.
+// Run with `cargo bench`.
+
+use std::fs::File;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use ndsutils::crypto::{blowfish_nds, init_keycode, load_encr_data, KeyLevel};
+
+fn bench(name: &str, iterations: u32, mut f: impl FnMut()) {
+    // Warm up caches and the branch predictor first
+    for _ in 0..iterations / 10 {
+        f();
+    }
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let elapsed = start.elapsed();
+
+    let per_iter = elapsed / iterations;
+    println!(
+        "{:<24} {:>10} iterations  {:>12?} total  {:>10?}/iter",
+        name,
+        iterations,
+        elapsed,
+        per_iter.max(Duration::from_nanos(1))
+    );
+}
+
+fn main() {
+    let mut f = File::open("encr_data.bin").expect("encr_data.bin is needed to benchmark");
+    let encr = load_encr_data(&mut f).unwrap();
+    let gamecode = u32::from_le_bytes(*b"ADAE");
+
+    let mut v = 0x0123_4567_89AB_CDEFu64;
+    bench("blowfish_nds encrypt", 1_000_000, || {
+        blowfish_nds(black_box(&mut v), &encr, true)
+    });
+    bench("blowfish_nds decrypt", 1_000_000, || {
+        blowfish_nds(black_box(&mut v), &encr, false)
+    });
+    bench("init_keycode level 3", 1_000, || {
+        black_box(init_keycode(&encr, black_box(gamecode), KeyLevel::Level3));
+    });
+}
@@ -0,0 +1,72 @@
+// Times reading every file of a 256MB NitroFS through a File and through MmapNdsRom.
+// Run with `cargo bench --features mmap --bench mmap`.
+
+use std::fs::{self, File};
+use std::hint::black_box;
+use std::io::{BufWriter, Read, Seek};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use ndsutils::builder::RomBuilder;
+use ndsutils::filesystem::NitroFS;
+use ndsutils::header::NDSCartridgeHeader;
+use ndsutils::mmap::MmapNdsRom;
+
+const FILE_COUNT: usize = 100;
+const FILE_SIZE: usize = 0x29_0000; // 100 of these come to just over 256MB
+
+// Reads every file in the ROM's NitroFS, returning the number of bytes read.
+fn read_all_files<R: Read + Seek>(nds: &mut R) -> u64 {
+    let hdr = NDSCartridgeHeader::parse_nds(&mut *nds).unwrap();
+    let nitro = NitroFS::parse(nds, &hdr).unwrap();
+    (0..nitro.fat.0.len() as u16)
+        .map(|id| black_box(nitro.fat.read_file_by_id(id, nds).unwrap()).len() as u64)
+        .sum()
+}
+
+fn bench(name: &str, iterations: u32, mut f: impl FnMut() -> u64) {
+    // The first pass also brings the file into the page cache
+    let total = f();
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let elapsed = start.elapsed();
+
+    let per_iter = elapsed / iterations;
+    println!(
+        "{:<24} {:>4} iterations  {:>12?} total  {:>10?}/iter  {:>8.1} MB/s",
+        name,
+        iterations,
+        elapsed,
+        per_iter,
+        total as f64 / per_iter.as_secs_f64() / 1e6
+    );
+}
+
+fn main() {
+    let rom_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("mmap_bench.nds");
+    if !rom_path.exists() {
+        let mut builder = RomBuilder::new();
+        builder.set_arm9(&[0; 0x800]).set_arm7(&[0; 0x800]);
+        for i in 0..FILE_COUNT {
+            builder.add_file(&format!("data/file{:03}.bin", i), &vec![i as u8; FILE_SIZE]);
+        }
+        builder
+            .build(&mut BufWriter::new(File::create(&rom_path).unwrap()))
+            .unwrap();
+    }
+    println!(
+        "{} files from a {}MB ROM",
+        FILE_COUNT,
+        fs::metadata(&rom_path).unwrap().len() >> 20
+    );
+
+    bench("File", 10, || {
+        read_all_files(&mut File::open(&rom_path).unwrap())
+    });
+    bench("MmapNdsRom", 10, || {
+        read_all_files(&mut MmapNdsRom::open(&rom_path).unwrap())
+    });
+}
@@ -0,0 +1,68 @@
+// A small command-line parser: positional arguments, boolean --flags, and --options that
+// take a value (either "--option value" or "--option=value").
+pub struct Args {
+    positional: Vec<String>,
+    flags: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    // `value_options` lists the options that consume a value and `flags` the boolean ones, both
+    // without their leading dashes; anything else starting with "--" is an error.
+    pub fn parse<I: Iterator<Item = String>>(
+        mut args: I,
+        value_options: &[&str],
+        flags: &[&str],
+    ) -> Result<Args, String> {
+        let mut parsed = Args {
+            positional: vec![],
+            flags: vec![],
+            options: vec![],
+        };
+
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                parsed.positional.push(arg);
+                continue;
+            };
+
+            if let Some((name, value)) = name.split_once('=') {
+                if !value_options.contains(&name) {
+                    return Err(format!("unknown option --{}", name));
+                }
+                parsed.options.push((name.to_string(), value.to_string()));
+            } else if value_options.contains(&name) {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("--{} expects a value", name))?;
+                parsed.options.push((name.to_string(), value));
+            } else if flags.contains(&name) {
+                parsed.flags.push(name.to_string());
+            } else {
+                return Err(format!("unknown option --{}", name));
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    // The i-th positional argument, which the usage string calls `what`.
+    pub fn positional(&self, i: usize, what: &str) -> Result<&str, String> {
+        self.positional
+            .get(i)
+            .map(String::as_str)
+            .ok_or_else(|| format!("missing <{}> argument", what))
+    }
+}
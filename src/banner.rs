@@ -0,0 +1,276 @@
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use crate::crc::bios_get_crc16;
+use crate::error::NdsError;
+use crate::header::NDSCartridgeHeader;
+use crate::png;
+
+// Number of title languages, in ROM order: Japanese, English, French, German,
+// Italian, Spanish, Chinese (version 2+), Korean (version 3+).
+pub const BANNER_LANGUAGES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BannerLanguage {
+    Japanese = 0,
+    English = 1,
+    French = 2,
+    German = 3,
+    Italian = 4,
+    Spanish = 5,
+    Chinese = 6,
+    Korean = 7,
+}
+
+impl BannerLanguage {
+    // Every language, in the order the titles are stored.
+    pub const ALL: [BannerLanguage; BANNER_LANGUAGES] = [
+        BannerLanguage::Japanese,
+        BannerLanguage::English,
+        BannerLanguage::French,
+        BannerLanguage::German,
+        BannerLanguage::Italian,
+        BannerLanguage::Spanish,
+        BannerLanguage::Chinese,
+        BannerLanguage::Korean,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BannerLanguage::Japanese => "Japanese",
+            BannerLanguage::English => "English",
+            BannerLanguage::French => "French",
+            BannerLanguage::German => "German",
+            BannerLanguage::Italian => "Italian",
+            BannerLanguage::Spanish => "Spanish",
+            BannerLanguage::Chinese => "Chinese",
+            BannerLanguage::Korean => "Korean",
+        }
+    }
+}
+
+// The icon/title block found at banner_offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NDSBanner {
+    pub version: u16,
+    pub crc16: [u16; 4], // Across [20h..840h], [20h..940h], [20h..A40h] and [1240h..23C0h]
+    pub icon: [u8; 1024], // 32x32 palette indices, row-major (de-tiled from 4bpp 8x8 tiles)
+    pub palette: [u16; 16], // BGR555, entry 0 is transparent
+    pub titles: [[u16; 128]; BANNER_LANGUAGES], // UCS-2, NUL-padded
+}
+
+impl NDSBanner {
+    pub fn parse<R: Read + Seek>(
+        nds: &mut R,
+        hdr: &NDSCartridgeHeader,
+    ) -> Result<NDSBanner, NdsError> {
+        let banner_offset = hdr.banner_offset;
+        if banner_offset == 0 {
+            return Err(NdsError::UnsupportedFormat("ROM has no banner".to_string()));
+        }
+
+        nds.seek(SeekFrom::Start(banner_offset as u64))?;
+        Self::read(nds)
+    }
+
+    // Parses a banner on its own, as extracted from a ROM.
+    pub fn from_bytes(data: &[u8]) -> Result<NDSBanner, NdsError> {
+        Self::read(&mut Cursor::new(data))
+    }
+
+    // Reads a banner starting at the current position.
+    fn read<R: Read + Seek>(nds: &mut R) -> Result<NDSBanner, NdsError> {
+        let version = nds.read_u16::<LittleEndian>()?;
+        let mut crc16 = [0u16; 4];
+        nds.read_u16_into::<LittleEndian>(&mut crc16)?;
+        nds.seek(SeekFrom::Current(0x16))?;
+
+        // Each 8x8 tile holds 32 bytes of 4bpp pixels, low nibble first
+        let mut tiles = [0u8; 0x200];
+        nds.read_exact(&mut tiles)?;
+        let mut icon = [0u8; 1024];
+        for (i, b) in tiles.iter().enumerate() {
+            let tile = i / 32;
+            let x = (tile % 4) * 8 + (i % 4) * 2;
+            let y = (tile / 4) * 8 + (i % 32) / 4;
+            icon[y * 32 + x] = b & 0xF;
+            icon[y * 32 + x + 1] = b >> 4;
+        }
+
+        let mut palette = [0u16; 16];
+        nds.read_u16_into::<LittleEndian>(&mut palette)?;
+
+        let languages = language_count(version).ok_or_else(|| {
+            NdsError::UnsupportedFormat(format!("banner version {:#06x}", version))
+        })?;
+
+        let mut titles = [[0u16; 128]; BANNER_LANGUAGES];
+        for title in &mut titles[..languages] {
+            nds.read_u16_into::<LittleEndian>(title)?;
+        }
+
+        Ok(NDSBanner {
+            version,
+            crc16,
+            icon,
+            palette,
+            titles,
+        })
+    }
+
+    // Number of titles present, which depends on the banner version.
+    pub fn language_count(&self) -> usize {
+        language_count(self.version).unwrap_or(0)
+    }
+
+    // Whether this banner version carries a title for the language.
+    pub fn has_title(&self, lang: BannerLanguage) -> bool {
+        (lang as usize) < self.language_count()
+    }
+
+    /* Returns the title for the given language, empty if this banner version doesn't carry it.
+    A leading BOM is dropped, as are control characters other than the newlines separating
+    the title's lines. */
+    pub fn title(&self, lang: BannerLanguage) -> String {
+        if !self.has_title(lang) {
+            return String::new();
+        }
+
+        let title = &self.titles[lang as usize];
+        let len = title.iter().position(|&c| c == 0).unwrap_or(title.len());
+        let title = match title[..len] {
+            [0xFEFF, ref rest @ ..] => rest,
+            ref all => all,
+        };
+        String::from_utf16_lossy(title)
+            .chars()
+            .filter(|&c| c == '\n' || !c.is_control())
+            .collect()
+    }
+
+    // The icon as 32x32 RGBA8 pixels. Palette entry 0 is fully transparent.
+    pub fn icon_rgba(&self) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.icon.len() * 4);
+        for &index in self.icon.iter() {
+            let c = self.palette[index as usize];
+            let expand = |v: u16| ((v & 0x1F) << 3 | (v & 0x1F) >> 2) as u8;
+            rgba.extend_from_slice(&[
+                expand(c),
+                expand(c >> 5),
+                expand(c >> 10),
+                if index == 0 { 0 } else { 0xFF },
+            ]);
+        }
+        rgba
+    }
+
+    pub fn export_icon_png(&self, path: &Path) -> Result<(), NdsError> {
+        let mut w = BufWriter::new(File::create(path)?);
+        png::write_rgba(&mut w, 32, 32, &self.icon_rgba())?;
+        w.flush()?;
+        Ok(())
+    }
+
+    // The CRC16s this banner should carry, one per title block its version adds. The DSi
+    // animation CRC isn't covered, as the animation data isn't held here.
+    pub fn computed_crcs(&self) -> Vec<u16> {
+        let data = self.crc_region();
+        (6..=self.language_count())
+            .map(|languages| bios_get_crc16(&data[..0x820 + (languages - 6) * 0x100]))
+            .collect()
+    }
+
+    pub fn validate_crc(&self) -> bool {
+        let computed = self.computed_crcs();
+        !computed.is_empty() && computed[..] == self.crc16[..computed.len()]
+    }
+
+    pub fn recompute_crc(&mut self) {
+        let computed = self.computed_crcs();
+        for (stored, computed) in self.crc16.iter_mut().zip(computed) {
+            *stored = computed;
+        }
+    }
+
+    /* Writes the banner back at banner_offset, updating its CRC16s first. Only the parts held
+    here are written; the reserved bytes and any DSi animation data are left as they are. */
+    pub fn write_to<W: Write + Seek>(
+        &mut self,
+        w: &mut W,
+        hdr: &NDSCartridgeHeader,
+    ) -> Result<(), NdsError> {
+        let banner_offset = hdr.banner_offset;
+        if banner_offset == 0 {
+            return Err(NdsError::UnsupportedFormat("ROM has no banner".to_string()));
+        }
+        self.recompute_crc();
+
+        w.seek(SeekFrom::Start(banner_offset as u64))?;
+        w.write_all(&self.version.to_le_bytes())?;
+        for crc in self.crc16 {
+            w.write_all(&crc.to_le_bytes())?;
+        }
+        w.seek(SeekFrom::Start(banner_offset as u64 + 0x20))?;
+        w.write_all(&self.crc_region())?;
+        Ok(())
+    }
+
+    /* The banner as laid out in the ROM, with its CRC16s recomputed: 840h bytes for a version 1
+    banner, and up to A40h for the later versions, which add titles. The DSi animation data
+    isn't held here, so a version 0103h banner comes out without it. This is a Vec rather
+    than a fixed-size array so that no version loses the titles it carries. */
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut crc16 = self.crc16;
+        for (stored, computed) in crc16.iter_mut().zip(self.computed_crcs()) {
+            *stored = computed;
+        }
+
+        let mut data = vec![0u8; 0x20];
+        data[..2].copy_from_slice(&self.version.to_le_bytes());
+        for (i, crc) in crc16.iter().enumerate() {
+            data[2 + i * 2..4 + i * 2].copy_from_slice(&crc.to_le_bytes());
+        }
+        data.extend_from_slice(&self.crc_region());
+        data
+    }
+
+    // The banner as laid out in the ROM from 20h up to the end of the last title.
+    fn crc_region(&self) -> Vec<u8> {
+        let mut data = vec![0u8; 0x220];
+        for (i, b) in data[..0x200].iter_mut().enumerate() {
+            let tile = i / 32;
+            let x = (tile % 4) * 8 + (i % 4) * 2;
+            let y = (tile / 4) * 8 + (i % 32) / 4;
+            *b = self.icon[y * 32 + x] & 0xF | self.icon[y * 32 + x + 1] << 4;
+        }
+        for (i, c) in self.palette.iter().enumerate() {
+            data[0x200 + i * 2..0x202 + i * 2].copy_from_slice(&c.to_le_bytes());
+        }
+        for title in &self.titles[..self.language_count()] {
+            data.extend(title.iter().flat_map(|c| c.to_le_bytes()));
+        }
+        data
+    }
+}
+
+// Size in bytes of a banner of the given version, as laid out in the ROM.
+pub fn banner_size(version: u16) -> u32 {
+    match version {
+        0x0103 => 0x23C0,
+        v if v & 0xFF == 2 => 0x940,
+        v if v & 0xFF == 3 => 0xA40,
+        _ => 0x840,
+    }
+}
+
+fn language_count(version: u16) -> Option<usize> {
+    match version & 0xFF {
+        1 => Some(6),
+        2 => Some(7),
+        3 => Some(8),
+        _ => None,
+    }
+}
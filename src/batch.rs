@@ -0,0 +1,137 @@
+// ------------------------------------------------------------
+// Running one operation over a collection of ROMs
+// ------------------------------------------------------------
+
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use crate::bootcode::ARM9Bootcode;
+use crate::crypto::decrypt_secure_area;
+use crate::error::NdsError;
+use crate::filesystem::NitroFAT;
+use crate::header::NDSCartridgeHeader;
+use crate::rom::create_rom_copy;
+use crate::validate::validate_rom;
+
+pub enum BatchOperation {
+    // Write a copy of each ROM, with its secure area decrypted using this encr_data.bin
+    Decrypt(Box<[u32; 1042]>),
+    // Run validate_rom over each ROM; nothing is written
+    Validate,
+    // Write a <name>.txt summary of each ROM's header
+    ExtractInfo,
+}
+
+// The outcome for one input. A failed ROM doesn't stop the batch; its error is the summary.
+pub struct BatchResult {
+    pub filename: PathBuf,
+    pub success: bool,
+    pub summary: String,
+}
+
+/* Applies the operation to every input in turn, writing any output under output_dir with the
+input's file name. Only failing to create output_dir is an error for the whole batch. */
+pub fn batch_process(
+    inputs: &[PathBuf],
+    operation: BatchOperation,
+    output_dir: &Path,
+) -> Result<Vec<BatchResult>, NdsError> {
+    if !matches!(operation, BatchOperation::Validate) {
+        fs::create_dir_all(output_dir)?;
+    }
+
+    Ok(inputs
+        .iter()
+        .map(|input| {
+            let outcome = match input.file_name() {
+                Some(name) => process_one(input, &operation, &output_dir.join(name)),
+                None => Err(NdsError::InvalidInput(format!(
+                    "{} is not a file",
+                    input.display()
+                ))),
+            };
+            let (success, summary) = match outcome {
+                Ok(outcome) => outcome,
+                Err(e) => (false, e.to_string()),
+            };
+            BatchResult {
+                filename: input.clone(),
+                success,
+                summary,
+            }
+        })
+        .collect())
+}
+
+// Runs the operation on one ROM, returning whether it succeeded and a one-line summary.
+fn process_one(
+    input: &Path,
+    operation: &BatchOperation,
+    output: &Path,
+) -> Result<(bool, String), NdsError> {
+    let mut rom = File::open(input)?;
+
+    match operation {
+        BatchOperation::Decrypt(encr) => {
+            let mut hdr = NDSCartridgeHeader::parse_nds(&mut rom)?;
+            let mut arm9 = ARM9Bootcode::new(&mut rom, &hdr)?;
+            let summary = if !arm9.secure_area_present {
+                "no secure area, copied as-is"
+            } else if !arm9.secure_area_encrypted {
+                "already decrypted, copied as-is"
+            } else {
+                decrypt_secure_area(&mut arm9, encr, hdr.gamecode, false)?;
+                "decrypted"
+            };
+
+            let mut out = create_rom_copy(&mut rom, output)?;
+            arm9.write_to(&mut out, &hdr, false)?;
+            hdr.write_to(&mut out)?;
+
+            Ok((true, summary.to_string()))
+        }
+        BatchOperation::Validate => {
+            let checks = validate_rom(&mut rom)?;
+            let failed: Vec<&str> = checks
+                .iter()
+                .filter(|c| !c.passed)
+                .map(|c| c.name.as_str())
+                .collect();
+            let summary = match failed.len() {
+                0 => format!("all {} checks passed", checks.len()),
+                n => format!(
+                    "{} of {} checks failed: {}",
+                    n,
+                    checks.len(),
+                    failed.join(", ")
+                ),
+            };
+            Ok((failed.is_empty(), summary))
+        }
+        BatchOperation::ExtractInfo => {
+            let hdr = NDSCartridgeHeader::parse_nds(&mut rom)?;
+            let fat = NitroFAT::parse(&mut rom, &hdr)?;
+
+            let mut info = String::new();
+            writeln!(info, "title: {}", hdr.game_title()).unwrap();
+            writeln!(info, "game code: {}", hdr.game_code_display()).unwrap();
+            writeln!(info, "maker code: {}", hdr.maker_code_str()).unwrap();
+            writeln!(info, "unit code: {}", hdr.unit_code()).unwrap();
+            writeln!(info, "region: {}", hdr.region()).unwrap();
+            writeln!(info, "version: {}", hdr.romversion[0]).unwrap();
+            writeln!(info, "capacity: {:#x}", hdr.rom_capacity_bytes()).unwrap();
+            writeln!(info, "used size: {:#x}", { hdr.total_used_rom_size }).unwrap();
+            writeln!(info, "files: {}", fat.0.len()).unwrap();
+
+            let mut info_path = output.as_os_str().to_owned();
+            info_path.push(".txt");
+            fs::write(info_path, info)?;
+
+            Ok((
+                true,
+                format!("{} [{}]", hdr.game_title(), hdr.game_code_display()),
+            ))
+        }
+    }
+}
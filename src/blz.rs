@@ -0,0 +1,212 @@
+// ------------------------------------------------------------
+// BLZ ("bottom LZ"), the LZ77 variant used for compressed ARM9s
+// ------------------------------------------------------------
+
+/* A BLZ image is decompressed from its end backwards, so that it can be unpacked in place.
+It is laid out as [uncompressed prefix][compressed data][padding][footer]. The 8-byte footer
+holds, at end-8, the size of the compressed data plus padding and footer (bits 0..23) and the
+size of the padding plus footer (bits 24..31), and at end-4 how many bytes longer the
+decompressed image is. An image with a zero size increase isn't compressed at all.
+
+The compressed data is read backwards: a flag byte, most significant bit first, says whether
+each of the next eight tokens is a literal byte or a two-byte back-reference of 4 bits length
+(3..18 bytes) and 12 bits distance (3..4098 bytes). */
+
+use crate::error::NdsError;
+
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 0x12;
+const MIN_DISTANCE: usize = 3;
+const MAX_DISTANCE: usize = 0x1002;
+
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, NdsError> {
+    let bad = |what: &str| NdsError::UnsupportedFormat(format!("BLZ image: {}", what));
+
+    if data.len() < 8 {
+        return Err(bad("too short for a footer"));
+    }
+    let footer = &data[data.len() - 8..];
+    let inc_len = u32::from_le_bytes(footer[4..8].try_into().unwrap()) as usize;
+    if inc_len == 0 {
+        // Stored as-is, apart from the footer
+        return Ok(data[..data.len() - 4].to_vec());
+    }
+
+    let enc_len = (u32::from_le_bytes(footer[0..4].try_into().unwrap()) & 0xFFFFFF) as usize;
+    let hdr_len = footer[3] as usize;
+    if hdr_len < 8 || enc_len < hdr_len || enc_len > data.len() {
+        return Err(bad("footer sizes are out of range"));
+    }
+
+    let dec_len = data.len() - enc_len;
+    let raw_len = data.len() + inc_len;
+    let mut out = vec![0u8; raw_len];
+    out[..dec_len].copy_from_slice(&data[..dec_len]);
+
+    // Walk both buffers from their ends towards the start
+    let mut src = data.len() - hdr_len;
+    let mut dst = raw_len;
+    let mut flags = 0u8;
+    let mut mask = 0u8;
+    while dst > dec_len {
+        mask >>= 1;
+        if mask == 0 {
+            if src == dec_len {
+                break;
+            }
+            src -= 1;
+            flags = data[src];
+            mask = 0x80;
+        }
+
+        if flags & mask == 0 {
+            if src == dec_len {
+                break;
+            }
+            src -= 1;
+            dst -= 1;
+            out[dst] = data[src];
+        } else {
+            if src < dec_len + 2 {
+                break;
+            }
+            let token = ((data[src - 1] as usize) << 8) | data[src - 2] as usize;
+            src -= 2;
+
+            let len = ((token >> 12) + MIN_MATCH).min(dst - dec_len);
+            let distance = (token & 0xFFF) + MIN_DISTANCE;
+            if dst + distance > raw_len {
+                return Err(bad("back-reference past the end of the image"));
+            }
+            for _ in 0..len {
+                dst -= 1;
+                out[dst] = out[dst + distance];
+            }
+        }
+    }
+
+    if dst != dec_len {
+        return Err(bad("compressed data ends early"));
+    }
+    Ok(out)
+}
+
+/* Compresses data into a BLZ image, leaving at least the first `keep` bytes stored as they
+are. This follows the greedy search of the usual BLZ encoder: the longest match within reach,
+the nearest on a tie, never overlapping the bytes it copies. As the data is compressed from the
+end, the split between stored prefix and compressed data is put wherever the image comes out
+smallest. Data that doesn't shrink is stored with a footer saying it isn't compressed, after
+padding it to a multiple of 4 bytes. */
+pub fn compress(data: &[u8], keep: usize) -> Vec<u8> {
+    let raw_len = data.len();
+    let raw_end = raw_len - keep.min(raw_len);
+
+    // Work on the data reversed, so that compression runs forwards
+    let inv: Vec<u8> = data.iter().rev().copied().collect();
+    let mut chains = MatchChains::new(raw_len);
+
+    let mut pak: Vec<u8> = Vec::with_capacity(raw_len + raw_len / 8 + 1);
+    let mut flag_pos = 0;
+    let mut mask = 0u8;
+    let mut raw = 0;
+    // The smallest (compressed, still stored) split seen so far
+    let (mut pak_tmp, mut raw_tmp) = (0, raw_len);
+    while raw < raw_end {
+        mask >>= 1;
+        if mask == 0 {
+            flag_pos = pak.len();
+            pak.push(0);
+            mask = 0x80;
+        }
+
+        let (len, distance) = chains.longest_match(&inv, raw, raw_end);
+        let step = if len >= MIN_MATCH {
+            pak[flag_pos] |= mask;
+            let token = ((len - MIN_MATCH) << 12) | (distance - MIN_DISTANCE);
+            pak.push((token >> 8) as u8);
+            pak.push(token as u8);
+            len
+        } else {
+            pak.push(inv[raw]);
+            1
+        };
+        for pos in raw..raw + step {
+            chains.insert(&inv, pos);
+        }
+        raw += step;
+
+        if pak.len() + raw_len - raw < pak_tmp + raw_tmp {
+            pak_tmp = pak.len();
+            raw_tmp = raw_len - raw;
+        }
+    }
+
+    let padded_len = (raw_tmp + pak_tmp + 3) & !3;
+    if pak_tmp == 0 || padded_len + 8 >= raw_len {
+        let mut out = data.to_vec();
+        out.resize((raw_len + 3) & !3, 0);
+        out.extend_from_slice(&[0; 4]);
+        return out;
+    }
+
+    let mut out = Vec::with_capacity(padded_len + 8);
+    out.extend_from_slice(&data[..raw_tmp]);
+    out.extend(pak[..pak_tmp].iter().rev());
+    out.resize(padded_len, 0xFF);
+
+    let hdr_len = 8 + padded_len - (raw_tmp + pak_tmp);
+    let enc_len = pak_tmp + hdr_len;
+    let inc_len = raw_len - padded_len - 8;
+    out.extend_from_slice(&(enc_len as u32 | (hdr_len as u32) << 24).to_le_bytes());
+    out.extend_from_slice(&(inc_len as u32).to_le_bytes());
+    out
+}
+
+// Earlier positions of the data, chained by the byte found there, nearest first.
+struct MatchChains {
+    head: [Option<usize>; 256],
+    prev: Vec<Option<usize>>,
+}
+
+impl MatchChains {
+    fn new(len: usize) -> Self {
+        MatchChains {
+            head: [None; 256],
+            prev: vec![None; len],
+        }
+    }
+
+    fn insert(&mut self, data: &[u8], pos: usize) {
+        let b = data[pos] as usize;
+        self.prev[pos] = self.head[b];
+        self.head[b] = Some(pos);
+    }
+
+    // The longest earlier copy of the data at `pos`, as (length, distance).
+    fn longest_match(&self, data: &[u8], pos: usize, end: usize) -> (usize, usize) {
+        let (mut best_len, mut best_distance) = (0, 0);
+        let mut candidate = self.head[data[pos] as usize];
+        while let Some(from) = candidate {
+            let distance = pos - from;
+            if distance > MAX_DISTANCE {
+                break;
+            }
+            candidate = self.prev[from];
+            if distance < MIN_DISTANCE {
+                continue;
+            }
+
+            let max_len = MAX_MATCH.min(distance).min(end - pos);
+            let len = (0..max_len)
+                .take_while(|&i| data[pos + i] == data[from + i])
+                .count();
+            if len > best_len {
+                (best_len, best_distance) = (len, distance);
+                if len == MAX_MATCH {
+                    break;
+                }
+            }
+        }
+        (best_len, best_distance)
+    }
+}
@@ -0,0 +1,375 @@
+use std::fmt::Write as _;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+use crate::blz;
+use crate::crc::bios_get_crc16;
+use crate::crypto::bytes_as_u64_slice;
+use crate::error::NdsError;
+use crate::header::NDSCartridgeHeader;
+
+// The secure area ID ("encryObj") as found after decryption, and the pattern it
+// is replaced with in decrypted dumps.
+pub const SECURE_AREA_ID: u64 = u64::from_le_bytes(*b"encryObj");
+pub const SECURE_AREA_ID_DECRYPTED: u64 = 0xE7FFDEFFE7FFDEFF;
+
+// The ARM9's module parameters end with the "nitrocode" pair DEC00621h, 2106C0DEh.
+const MODULE_PARAMS_MAGIC: [u8; 8] = [0x21, 0x06, 0xC0, 0xDE, 0xDE, 0xC0, 0x06, 0x21];
+const MODULE_PARAMS_MAGIC_OFFSET: usize = 0x1C;
+// RAM address of the end of the BLZ-compressed static module, or 0 if it isn't compressed
+const MODULE_PARAMS_COMPRESSED_END: usize = 0x14;
+// How much of the start of a compressed ARM9 is left uncompressed
+const ARM9_BLZ_STORED_LEN: usize = 0x4000;
+
+// The first 10h bytes of the (decrypted) ARM9 secure area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SecureAreaHeader {
+    pub id: u64,          // Secure area ID, SECURE_AREA_ID_DECRYPTED in decrypted dumps
+    pub random: [u16; 2], // Random filler
+    pub crc16_aux: u16,   // A second CRC16, which isn't checked here
+    pub crc16: u16,       // CRC16 of [010h..800h]
+}
+
+impl SecureAreaHeader {
+    pub const SIZE: usize = 0x10;
+
+    // Reads the header from the start of the secure area, which must be at least 10h bytes.
+    pub fn parse(data: &[u8]) -> Self {
+        let u16_at = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+        SecureAreaHeader {
+            id: u64::from_le_bytes(data[..8].try_into().unwrap()),
+            random: [u16_at(0x8), u16_at(0xA)],
+            crc16_aux: u16_at(0xC),
+            crc16: u16_at(0xE),
+        }
+    }
+
+    pub fn as_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&self.id.to_le_bytes());
+        bytes[0x8..0xA].copy_from_slice(&self.random[0].to_le_bytes());
+        bytes[0xA..0xC].copy_from_slice(&self.random[1].to_le_bytes());
+        bytes[0xC..0xE].copy_from_slice(&self.crc16_aux.to_le_bytes());
+        bytes[0xE..0x10].copy_from_slice(&self.crc16.to_le_bytes());
+        bytes
+    }
+}
+
+/* Describes the secure area header field by field, one line each, with its offset. While the
+secure area is still encrypted, the fields mean nothing, so the 10h bytes are shown as raw hex
+instead. The CRC16 line also gives the value computed over the secure area. */
+pub fn dump_secure_area_header(arm9: &ARM9Bootcode) -> String {
+    if !arm9.secure_area_present || arm9.raw_data.len() < 0x800 {
+        return "No ARM9 secure area\n".to_string();
+    }
+
+    let mut out = String::new();
+    if arm9.secure_area_encrypted {
+        let hex: Vec<String> = arm9.raw_data[..SecureAreaHeader::SIZE]
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect();
+        writeln!(out, "000h  {:<18} {}", "Encrypted", hex.join(" ")).unwrap();
+        return out;
+    }
+
+    let header = SecureAreaHeader::parse(&arm9.raw_data);
+    let id_note = match header.id {
+        SECURE_AREA_ID_DECRYPTED => " (decrypted dump)",
+        SECURE_AREA_ID => " (\"encryObj\")",
+        _ => " (unexpected)",
+    };
+    let actual = bios_get_crc16(&arm9.raw_data[0x10..0x800]);
+    let rows = [
+        (
+            0x0,
+            "Secure area ID",
+            format!("{:016X}{}", header.id, id_note),
+        ),
+        (
+            0x8,
+            "Random",
+            format!("{:04X} {:04X}", header.random[0], header.random[1]),
+        ),
+        (0xC, "CRC16 (aux)", format!("{:#06x}", header.crc16_aux)),
+        (
+            0xE,
+            "CRC16",
+            format!("{:#06x} (actual {:#06x})", header.crc16, actual),
+        ),
+    ];
+    for (offset, name, value) in rows {
+        writeln!(out, "{:03X}h  {:<18} {}", offset, name, value).unwrap();
+    }
+    out
+}
+
+// Represents the contents of the ARM9 bootcode, as well as
+// information about its secure area.
+pub struct ARM9Bootcode {
+    pub raw_data: Vec<u8>,
+    pub secure_area_present: bool, // Determined by start address (4000h..8000h)
+    pub secure_area_encrypted: bool,
+}
+
+impl ARM9Bootcode {
+    // Fails if the header places the ARM9 past the end of the ROM.
+    pub fn new<R: Read + Seek>(
+        nds: &mut R,
+        hdr: &NDSCartridgeHeader,
+    ) -> Result<ARM9Bootcode, NdsError> {
+        // Any ARM9 starting within 4000h..7FFFh is loaded through the secure area, unless the
+        // header disables it. Its first 2K are the encrypted part, so an ARM9 shorter than that
+        // can't have one.
+        let arm9off: u64 = hdr.arm9off as u64;
+        let secure_area_present = hdr.arm9off >= 0x4000
+            && hdr.arm9off < 0x8000
+            && hdr.arm9size >= 0x800
+            && !hdr.secure_area_disable();
+
+        check_section(nds, hdr.arm9off, hdr.arm9size, "ARM9")?;
+        nds.seek(SeekFrom::Start(arm9off))?;
+
+        let mut contents: Vec<u8> = vec![0; hdr.arm9size as usize];
+        nds.read_exact(&mut contents)?;
+
+        let secure_area_encrypted = secure_area_present
+            && contents.get(..8) != Some(&SECURE_AREA_ID_DECRYPTED.to_le_bytes()[..]);
+
+        Ok(ARM9Bootcode {
+            raw_data: contents,
+            secure_area_present,
+            secure_area_encrypted,
+        })
+    }
+
+    // new over an in-memory ROM image.
+    pub fn from_bytes(rom: &[u8], hdr: &NDSCartridgeHeader) -> Result<ARM9Bootcode, NdsError> {
+        Self::new(&mut Cursor::new(rom), hdr)
+    }
+
+    /* The first 8 bytes of the secure area: the encrypted area ID, "encryObj" once the rest is
+    decrypted, or SECURE_AREA_ID_DECRYPTED in a decrypted dump. None without a secure area. */
+    pub fn secure_area_id(&self) -> Option<[u8; 8]> {
+        if !self.secure_area_present {
+            return None;
+        }
+        self.raw_data.get(..8)?.try_into().ok()
+    }
+
+    /* Writes the bootcode back at arm9off. If it has grown past arm9size this fails with
+    SizeChanged, unless allow_resize is set; either way, updating the header (and making sure
+    nothing is overwritten past the old end) is up to the caller. */
+    pub fn write_to<W: Write + Seek>(
+        &self,
+        w: &mut W,
+        hdr: &NDSCartridgeHeader,
+        allow_resize: bool,
+    ) -> Result<(), NdsError> {
+        let arm9size = hdr.arm9size;
+        if self.raw_data.len() > arm9size as usize && !allow_resize {
+            return Err(NdsError::SizeChanged {
+                expected: arm9size as u64,
+                got: self.raw_data.len() as u64,
+            });
+        }
+
+        w.seek(SeekFrom::Start(hdr.arm9off as u64))?;
+        w.write_all(&self.raw_data)?;
+        Ok(())
+    }
+
+    /* Views the bootcode as 64-bit words (in native byte order), as the Blowfish
+    routines see it. Any trailing bytes past the last whole word are left out.
+    Fails if the buffer isn't 8-byte aligned, which the global allocator avoids in practice. */
+    pub fn as_u64_slice(&self) -> Result<&[u64], NdsError> {
+        let len = self.raw_data.len() & !7;
+        bytes_as_u64_slice(&self.raw_data[..len])
+    }
+}
+
+// Represents the contents of the ARM7 bootcode. Unlike the ARM9, it has no
+// secure area and is never encrypted.
+pub struct ARM7Bootcode {
+    pub raw_data: Vec<u8>,
+}
+
+impl ARM7Bootcode {
+    // As for the ARM9, fails if the header places the ARM7 past the end of the ROM.
+    pub fn new<R: Read + Seek>(
+        nds: &mut R,
+        hdr: &NDSCartridgeHeader,
+    ) -> Result<ARM7Bootcode, NdsError> {
+        check_section(nds, hdr.arm7off, hdr.arm7size, "ARM7")?;
+        nds.seek(SeekFrom::Start(hdr.arm7off as u64))?;
+
+        let mut contents: Vec<u8> = vec![0; hdr.arm7size as usize];
+        nds.read_exact(&mut contents)?;
+
+        Ok(ARM7Bootcode { raw_data: contents })
+    }
+
+    pub fn dump_to_writer<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        w.write_all(&self.raw_data)
+    }
+}
+
+// Fails if [offset, offset + size) runs past the end of the image.
+pub(crate) fn check_section<R: Seek>(
+    r: &mut R,
+    offset: u32,
+    size: u32,
+    name: &str,
+) -> Result<(), NdsError> {
+    let len = r.seek(SeekFrom::End(0))?;
+    if offset as u64 + size as u64 > len {
+        return Err(NdsError::UnsupportedFormat(format!(
+            "{} at {:#x}..{:#x} runs past the end of the ROM ({:#x})",
+            name,
+            offset,
+            offset as u64 + size as u64,
+            len
+        )));
+    }
+    Ok(())
+}
+
+// Offset of the module parameters within the ARM9, found by their trailing magic.
+fn find_module_params(code: &[u8]) -> Option<usize> {
+    (MODULE_PARAMS_MAGIC_OFFSET..code.len().saturating_sub(MODULE_PARAMS_MAGIC.len() - 1))
+        .step_by(4)
+        .find(|&i| code[i..i + MODULE_PARAMS_MAGIC.len()] == MODULE_PARAMS_MAGIC)
+        .map(|i| i - MODULE_PARAMS_MAGIC_OFFSET)
+}
+
+fn compressed_end(code: &[u8], params: usize) -> u32 {
+    let field = params + MODULE_PARAMS_COMPRESSED_END;
+    u32::from_le_bytes(code[field..field + 4].try_into().unwrap())
+}
+
+/* Whether the ARM9 is BLZ-compressed, going by its module parameters: their compressed end
+address is only set, to somewhere within the loaded ARM9, when the code unpacks itself. */
+pub fn is_arm9_compressed(arm9: &ARM9Bootcode, hdr: &NDSCartridgeHeader) -> bool {
+    let Some(params) = find_module_params(&arm9.raw_data) else {
+        return false;
+    };
+
+    let end = compressed_end(&arm9.raw_data, params);
+    let raddr = hdr.arm9raddr;
+    end > raddr && end - raddr <= hdr.arm9size
+}
+
+// What is found at the ARM9 entry point, see analyze_arm9_entry_point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryPointKind {
+    // A B or BL, or an LDR of the PC from a literal, with the address it goes to
+    DirectBranch { target: u32 },
+    // Addresses into the loaded ARM9 rather than code
+    PointerTable,
+    // Anything else, including ordinary startup code and an entry point still encrypted
+    Unknown,
+}
+
+/* Looks at the first words at arm9entry, as loaded at arm9raddr, to tell a direct branch (the
+usual first instruction of homebrew) from a table of pointers, as some hacked ROMs have. The
+entry point is always ARM code. The first word is checked for a B/BL or an LDR PC, [PC, #imm];
+failing that, if it and the next word are both word-aligned addresses within the loaded ARM9,
+it is taken for a pointer table. */
+pub fn analyze_arm9_entry_point(arm9: &ARM9Bootcode, hdr: &NDSCartridgeHeader) -> EntryPointKind {
+    let (entry, raddr) = (hdr.arm9entry, hdr.arm9raddr);
+    let Some(offset) = entry.checked_sub(raddr).map(|o| o as usize) else {
+        return EntryPointKind::Unknown;
+    };
+    if entry % 4 != 0 || (arm9.secure_area_encrypted && offset < 0x800) {
+        return EntryPointKind::Unknown;
+    }
+    let word_at = |offset: usize| -> Option<u32> {
+        let bytes = arm9.raw_data.get(offset..offset.checked_add(4)?)?;
+        Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    let Some(insn) = word_at(offset) else {
+        return EntryPointKind::Unknown;
+    };
+
+    // The PC reads as the instruction's address plus 8
+    if insn >> 28 != 0xF && insn & 0x0E00_0000 == 0x0A00_0000 {
+        let displacement = ((insn << 8) as i32 >> 6) as u32;
+        return EntryPointKind::DirectBranch {
+            target: entry.wrapping_add(8).wrapping_add(displacement),
+        };
+    }
+    if insn & 0x0F7F_F000 == 0x051F_F000 {
+        let imm = (insn & 0xFFF) as usize;
+        let literal = if insn & (1 << 23) != 0 {
+            (offset + 8).checked_add(imm)
+        } else {
+            (offset + 8).checked_sub(imm)
+        };
+        if let Some(target) = literal.and_then(word_at) {
+            return EntryPointKind::DirectBranch { target };
+        }
+    }
+
+    let loaded = raddr as u64..raddr as u64 + hdr.arm9size as u64;
+    let is_pointer =
+        |word: Option<u32>| word.is_some_and(|w| w % 4 == 0 && loaded.contains(&(w as u64)));
+    if is_pointer(Some(insn)) && is_pointer(word_at(offset + 4)) {
+        return EntryPointKind::PointerTable;
+    }
+    EntryPointKind::Unknown
+}
+
+/* Returns the ARM9 with its BLZ-compressed static module unpacked, and the compressed end
+address in its module parameters cleared so that it won't try to unpack itself again.
+An ARM9 that isn't compressed is returned as-is. */
+pub fn decompress_arm9_blz(arm9: &ARM9Bootcode) -> Result<Vec<u8>, NdsError> {
+    let params = find_module_params(&arm9.raw_data)
+        .ok_or_else(|| NdsError::UnsupportedFormat("ARM9 has no module parameters".to_string()))?;
+    if compressed_end(&arm9.raw_data, params) == 0 {
+        return Ok(arm9.raw_data.clone());
+    }
+
+    // The module parameters lie in the uncompressed prefix, so they stay where they were
+    let mut code = blz::decompress(&arm9.raw_data)?;
+    if find_module_params(&code) != Some(params) {
+        return Err(NdsError::UnsupportedFormat(
+            "ARM9 module parameters moved during decompression".to_string(),
+        ));
+    }
+    let field = params + MODULE_PARAMS_COMPRESSED_END;
+    code[field..field + 4].fill(0);
+
+    Ok(code)
+}
+
+/* BLZ-compresses an ARM9 binary, leaving its first 4000h bytes (the secure area and the module
+parameters) stored as they are. The caller is responsible for pointing the compressed end
+address in the module parameters at the end of the result, as loaded in RAM. */
+pub fn compress_arm9_blz(raw: &[u8]) -> Vec<u8> {
+    blz::compress(raw, ARM9_BLZ_STORED_LEN)
+}
+
+// The part of the secure area covered by its header's CRC16: [secure_area+10h..secure_area+800h]
+pub const SECURE_AREA_CRC_LEN: usize = 0x7F0;
+
+/* Check that the ARM9 secure area CRC16 in its header (at [secure_area+0Eh]) is correct.
+Either way, return the correct CRC16, assuming the contents of [secure_area+10h..secure_area+800h]
+are actually correct. Note that it is observed that nothing actually ensures this is correct in BIOS/firmware.
+Nonetheless, we should insert it when packing brew'd games.
+This takes a plain slice rather than a &[u8; 7F0h], as callers nearly always slice it out of a
+bootcode whose length is only known at runtime; a slice of any other length is an InvalidInput
+error rather than a panic. */
+pub fn check_secure_area_crc(
+    header: &SecureAreaHeader,
+    sec_area_slice: &[u8],
+) -> Result<(bool, u16), NdsError> {
+    if sec_area_slice.len() != SECURE_AREA_CRC_LEN {
+        return Err(NdsError::InvalidInput(format!(
+            "secure area CRC covers {:#x} bytes, but {:#x} were given",
+            SECURE_AREA_CRC_LEN,
+            sec_area_slice.len()
+        )));
+    }
+
+    let crc_correct = bios_get_crc16(sec_area_slice);
+    Ok((crc_correct == header.crc16, crc_correct))
+}
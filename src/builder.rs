@@ -0,0 +1,173 @@
+// ------------------------------------------------------------
+// Assembling a ROM image from its parts
+// ------------------------------------------------------------
+
+use std::collections::BTreeMap;
+use std::io::{Cursor, Write};
+
+use crate::banner::{banner_size, NDSBanner};
+use crate::crc::bios_get_crc16;
+use crate::error::NdsError;
+use crate::filesystem::{FATEntry, NitroFAT, NitroFNT};
+use crate::header::NDSCartridgeHeader;
+use crate::validate::validate_code_alignment;
+
+// Every section, and every file, starts on a 200h boundary
+const SECTION_ALIGN: usize = 0x200;
+
+// Where the ARM9 goes: right after the header and the rest of the first 16KB
+const ARM9_OFFSET: usize = 0x4000;
+
+/* Builds a ROM from a header, the two bootcodes, an optional banner and a set of NitroFS files.
+The sections are laid out in the usual order: header, ARM9, ARM7, overlay tables (always empty
+here), FNT, FAT, banner and the file data, each aligned to 200h with FFh in between. The offsets,
+sizes, device capacity and CRCs in the header are all filled in by build; anything else comes
+from the header given. */
+#[derive(Default)]
+pub struct RomBuilder {
+    header: NDSCartridgeHeader,
+    arm9: Vec<u8>,
+    arm7: Vec<u8>,
+    banner: Option<Vec<u8>>,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl RomBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_header(&mut self, hdr: NDSCartridgeHeader) -> &mut Self {
+        self.header = hdr;
+        self
+    }
+
+    pub fn set_arm9(&mut self, data: &[u8]) -> &mut Self {
+        self.arm9 = data.to_vec();
+        self
+    }
+
+    pub fn set_arm7(&mut self, data: &[u8]) -> &mut Self {
+        self.arm7 = data.to_vec();
+        self
+    }
+
+    // A raw banner, as read from another ROM. Its CRC16s are recomputed on build.
+    pub fn set_banner(&mut self, data: &[u8]) -> &mut Self {
+        self.banner = Some(data.to_vec());
+        self
+    }
+
+    // Adds a NitroFS file. The path is relative to the root, with '/' separators.
+    pub fn add_file(&mut self, path: &str, data: &[u8]) -> &mut Self {
+        self.files.push((path.to_string(), data.to_vec()));
+        self
+    }
+
+    pub fn build<W: Write>(&self, w: &mut W) -> Result<(), NdsError> {
+        if self.arm9.is_empty() || self.arm7.is_empty() {
+            return Err(NdsError::InvalidInput(
+                "a ROM needs both an ARM9 and an ARM7 binary".to_string(),
+            ));
+        }
+
+        let mut hdr = NDSCartridgeHeader::parse_nds_bytes(self.header.as_bytes())?;
+        let mut rom: Vec<u8> = vec![0; ARM9_OFFSET];
+
+        hdr.arm9off = place(&mut rom, &self.arm9)?;
+        hdr.arm9size = self.arm9.len() as u32;
+        hdr.arm7off = place(&mut rom, &self.arm7)?;
+        hdr.arm7size = self.arm7.len() as u32;
+        validate_code_alignment(&hdr)?;
+        hdr.arm9_overlay_offset = 0;
+        hdr.arm9_overlay_size = 0;
+        hdr.arm7_overlay_offset = 0;
+        hdr.arm7_overlay_size = 0;
+
+        let paths: Vec<&str> = self.files.iter().map(|(path, _)| path.as_str()).collect();
+        let fnt = NitroFNT::from_paths(&paths, 0)?;
+        let fnt_bytes = fnt.to_bytes();
+        hdr.fnt_offset = place(&mut rom, &fnt_bytes)?;
+        hdr.fnt_size = fnt_bytes.len() as u32;
+
+        // The FAT is filled in once the files have been placed
+        let file_count = self.files.len();
+        hdr.fat_offset = place(&mut rom, &vec![0; file_count * 8])?;
+        hdr.fat_size = (file_count * 8) as u32;
+
+        hdr.banner_offset = match &self.banner {
+            Some(banner) => {
+                let version = banner
+                    .get(..2)
+                    .map_or(0, |v| u16::from_le_bytes([v[0], v[1]]));
+                if banner.len() < banner_size(version) as usize {
+                    return Err(NdsError::InvalidInput(format!(
+                        "banner is {} bytes, but version {:#06x} needs {}",
+                        banner.len(),
+                        version,
+                        banner_size(version)
+                    )));
+                }
+                place(&mut rom, banner)?
+            }
+            None => 0,
+        };
+
+        let contents: BTreeMap<&str, &[u8]> = self
+            .files
+            .iter()
+            .map(|(path, data)| (path.as_str(), data.as_slice()))
+            .collect();
+        let mut fat = NitroFAT(vec![FATEntry { start: 0, end: 0 }; file_count]);
+        for (path, file_id) in fnt.iter() {
+            // Rebuilt with '/', as the FNT's PathBufs would use '\' on Windows
+            let components: Vec<_> = path.iter().map(|c| c.to_string_lossy()).collect();
+            let path = components.join("/");
+            let data = *contents.get(path.as_str()).ok_or_else(|| {
+                NdsError::InvalidInput(format!("no data for NitroFS file '{}'", path))
+            })?;
+            let start = place(&mut rom, data)?;
+            fat.0[file_id as usize] = FATEntry {
+                start,
+                end: start + data.len() as u32,
+            };
+        }
+
+        // The secure area CRC covers up to 8000h, so the image can't end before it
+        if rom.len() < 0x8000 {
+            rom.resize(0x8000, 0xFF);
+        }
+
+        hdr.total_used_rom_size = rom.len() as u32;
+        hdr.header_size = ARM9_OFFSET as u32;
+        let mut capacity = 0;
+        while (0x20000u64 << capacity) < rom.len() as u64 {
+            capacity += 1;
+        }
+        hdr.devicecaps = [capacity];
+        hdr.logo_crc = bios_get_crc16(&hdr.logo);
+
+        let mut cursor = Cursor::new(rom);
+        fat.write_to(&mut cursor, &hdr)?;
+        if self.banner.is_some() {
+            NDSBanner::parse(&mut cursor, &hdr)?.write_to(&mut cursor, &hdr)?;
+        }
+        hdr.write_to(&mut cursor)?;
+
+        w.write_all(cursor.get_ref())?;
+        Ok(())
+    }
+}
+
+// Appends a section at the next aligned offset, padding with FFh, and returns that offset.
+fn place(rom: &mut Vec<u8>, data: &[u8]) -> Result<u32, NdsError> {
+    let offset = rom.len().next_multiple_of(SECTION_ALIGN);
+    if offset + data.len() > u32::MAX as usize {
+        return Err(NdsError::InvalidInput(
+            "built ROM would exceed 4GB".to_string(),
+        ));
+    }
+    rom.resize(offset, 0xFF);
+    rom.extend_from_slice(data);
+    Ok(offset as u32)
+}
@@ -0,0 +1,65 @@
+use ndsutils::bootcode::ARM9Bootcode;
+use std::io::{self, Write};
+
+use ndsutils::crypto::{
+    decrypt_secure_area, dump_kbuf, init_keycode_traced, verify_secure_area_magic, KeyLevel,
+};
+use ndsutils::header::NDSCartridgeHeader;
+
+use super::{
+    encr_data, open_rom, write_rom_with_arm9, CommandResult, BAD_DECRYPTION, SECURE_AREA_DISABLED,
+};
+use crate::args::Args;
+
+pub const FLAGS: &[&str] = &["dump-keystate", "force-decrypt"];
+
+/* decrypt <input> <output> [--dump-keystate] [--force-decrypt]: writes a copy of the ROM with its
+ARM9 secure area decrypted. --dump-keystate prints the level 2 and 3 key buffers after every
+apply_keycode step. --force-decrypt writes the result even if the secure area ID doesn't come out
+right; that is expected for some dev ROMs, but with the wrong keys the output is garbage. */
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "input")?)?;
+    let out_path = args.positional(1, "output")?;
+
+    let mut ndshdr = NDSCartridgeHeader::parse_nds(&mut ndsfile)?;
+    let mut arm9code = ARM9Bootcode::new(&mut ndsfile, &ndshdr)?;
+
+    if ndshdr.secure_area_disable() {
+        eprintln!("warning: {}; nothing written", SECURE_AREA_DISABLED);
+        return Ok(());
+    }
+    if !arm9code.secure_area_present {
+        return Err("ROM has no ARM9 secure area".into());
+    }
+    if !arm9code.secure_area_encrypted {
+        return Err("ARM9 secure area is already decrypted".into());
+    }
+
+    let encr = encr_data(args)?;
+    if args.flag("dump-keystate") {
+        let mut dump: Vec<u8> = vec![];
+        for level in [KeyLevel::Level2, KeyLevel::Level3] {
+            init_keycode_traced(&encr, ndshdr.gamecode, level, |step, kbuf| {
+                writeln!(
+                    dump,
+                    "Level {} key buffer after apply_keycode step {}:",
+                    level as u8, step
+                )
+                .unwrap();
+                dump_kbuf(kbuf, &mut dump).unwrap();
+            });
+        }
+        io::stdout().write_all(&dump)?;
+    }
+    let force = args.flag("force-decrypt");
+    decrypt_secure_area(&mut arm9code, &encr, ndshdr.gamecode, force)
+        .map_err(|_| BAD_DECRYPTION)?;
+    if !verify_secure_area_magic(&arm9code) {
+        eprintln!("warning: {}; writing the result anyway", BAD_DECRYPTION);
+    }
+
+    write_rom_with_arm9(&mut ndsfile, out_path, &mut ndshdr, &arm9code)?;
+
+    println!("Decrypted secure area written to {}", out_path);
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::path::PathBuf;
+
+use colored::Colorize;
+use ndsutils::banner::{BannerLanguage, NDSBanner};
+use ndsutils::bootcode::{ARM7Bootcode, ARM9Bootcode};
+use ndsutils::error::NdsError;
+use ndsutils::filesystem::{read_entry, NitroFS};
+use ndsutils::header::NDSCartridgeHeader;
+
+use super::{open_rom, CommandResult};
+use crate::args::Args;
+
+// How many differing 16-byte rows of a binary to show, unless --verbose is given
+const MAX_HEX_ROWS: usize = 8;
+
+// One side of the comparison.
+struct Rom {
+    file: File,
+    hdr: NDSCartridgeHeader,
+    nitro: Option<NitroFS>, // None if the ROM has no file system
+}
+
+impl Rom {
+    fn open(path: &str) -> Result<Rom, Box<dyn Error>> {
+        let mut file = open_rom(path)?;
+        let hdr = NDSCartridgeHeader::parse_nds(&mut file)?;
+        let nitro = match hdr.fnt_size {
+            0 => None,
+            _ => Some(NitroFS::parse(&mut file, &hdr)?),
+        };
+        Ok(Rom { file, hdr, nitro })
+    }
+
+    fn banner_titles(&mut self) -> Vec<(BannerLanguage, String)> {
+        match NDSBanner::parse(&mut self.file, &self.hdr) {
+            Ok(banner) => BannerLanguage::ALL
+                .into_iter()
+                .filter(|&lang| banner.has_title(lang))
+                .map(|lang| (lang, banner.title(lang).replace('\n', " / ")))
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    // Every NitroFS file by path, with its file ID.
+    fn files(&self) -> BTreeMap<PathBuf, u16> {
+        match &self.nitro {
+            Some(nitro) => nitro.fnt.iter().collect(),
+            None => BTreeMap::new(),
+        }
+    }
+
+    fn read(&mut self, file_id: u16) -> Result<Vec<u8>, NdsError> {
+        match self
+            .nitro
+            .as_ref()
+            .and_then(|n| n.fat.0.get(file_id as usize))
+        {
+            Some(entry) => read_entry(&mut self.file, entry),
+            None => Ok(vec![]),
+        }
+    }
+}
+
+fn header_line((offset, name, raw, meaning): &(u32, &str, String, String)) -> String {
+    format!("{:03X}h  {:<24} {} {}", offset, name, raw, meaning)
+        .trim_end()
+        .to_string()
+}
+
+fn removed(line: String) {
+    println!("{}", format!("-{}", line).red());
+}
+
+fn added(line: String) {
+    println!("{}", format!("+{}", line).green());
+}
+
+fn section(name: &str) {
+    println!("{}", format!("@@ {} @@", name).cyan());
+}
+
+// Prints the 16-byte rows that differ between two binaries, as -/+ pairs of hex dumps.
+fn hex_diff(a: &[u8], b: &[u8], verbose: bool) {
+    let hex_row = |data: &[u8], offset: usize| -> String {
+        let row = data.get(offset..data.len().min(offset + 16)).unwrap_or(&[]);
+        row.iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let rows = a.len().max(b.len()).div_ceil(16);
+    let differing: Vec<usize> = (0..rows)
+        .map(|row| row * 16)
+        .filter(|&offset| hex_row(a, offset) != hex_row(b, offset))
+        .collect();
+
+    let shown = if verbose {
+        differing.len()
+    } else {
+        differing.len().min(MAX_HEX_ROWS)
+    };
+    for &offset in &differing[..shown] {
+        removed(format!("  {:08x}: {}", offset, hex_row(a, offset)));
+        added(format!("  {:08x}: {}", offset, hex_row(b, offset)));
+    }
+    if shown < differing.len() {
+        println!("  ... {} more differing rows", differing.len() - shown);
+    }
+}
+
+pub const FLAGS: &[&str] = &["verbose"];
+
+// diff <a> <b> [--verbose]: compares two ROMs section by section, printing only what differs.
+pub fn run(args: &Args) -> CommandResult {
+    let a_path = args.positional(0, "a")?;
+    let b_path = args.positional(1, "b")?;
+    let verbose = args.flag("verbose");
+    let mut a = Rom::open(a_path)?;
+    let mut b = Rom::open(b_path)?;
+
+    println!("{}", format!("--- {}", a_path).red());
+    println!("{}", format!("+++ {}", b_path).green());
+
+    let header_changes: Vec<_> = a
+        .hdr
+        .rows()
+        .into_iter()
+        .zip(b.hdr.rows())
+        .filter(|(row_a, row_b)| row_a.2 != row_b.2)
+        .collect();
+    if !header_changes.is_empty() {
+        section("header");
+        for (row_a, row_b) in header_changes {
+            removed(header_line(&row_a));
+            added(header_line(&row_b));
+        }
+    }
+
+    let arm9_a = ARM9Bootcode::new(&mut a.file, &a.hdr)?;
+    let arm9_b = ARM9Bootcode::new(&mut b.file, &b.hdr)?;
+    if arm9_a.raw_data != arm9_b.raw_data {
+        section("ARM9");
+        hex_diff(&arm9_a.raw_data, &arm9_b.raw_data, verbose);
+    }
+
+    let arm7_a = ARM7Bootcode::new(&mut a.file, &a.hdr)?;
+    let arm7_b = ARM7Bootcode::new(&mut b.file, &b.hdr)?;
+    if arm7_a.raw_data != arm7_b.raw_data {
+        section("ARM7");
+        hex_diff(&arm7_a.raw_data, &arm7_b.raw_data, verbose);
+    }
+
+    let titles_a = a.banner_titles();
+    let titles_b = b.banner_titles();
+    if titles_a != titles_b {
+        section("banner");
+        for (lang, title) in titles_a.iter().filter(|t| !titles_b.contains(t)) {
+            removed(format!("title ({}): {}", lang.name(), title));
+        }
+        for (lang, title) in titles_b.iter().filter(|t| !titles_a.contains(t)) {
+            added(format!("title ({}): {}", lang.name(), title));
+        }
+    }
+
+    let files_a = a.files();
+    let files_b = b.files();
+    let mut paths: Vec<&PathBuf> = files_a.keys().chain(files_b.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut files_section = false;
+    for path in paths {
+        let data_a = files_a.get(path).map(|&id| a.read(id)).transpose()?;
+        let data_b = files_b.get(path).map(|&id| b.read(id)).transpose()?;
+        if data_a == data_b {
+            continue;
+        }
+        if !files_section {
+            section("files");
+            files_section = true;
+        }
+
+        match (data_a, data_b) {
+            (Some(data), None) => removed(format!("{} ({} bytes)", path.display(), data.len())),
+            (None, Some(data)) => added(format!("{} ({} bytes)", path.display(), data.len())),
+            (Some(data_a), Some(data_b)) => {
+                println!(
+                    "~{} ({} -> {} bytes)",
+                    path.display(),
+                    data_a.len(),
+                    data_b.len()
+                );
+                hex_diff(&data_a, &data_b, verbose);
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+
+    Ok(())
+}
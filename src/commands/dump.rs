@@ -0,0 +1,44 @@
+use std::fs::File;
+use std::path::Path;
+
+use ndsutils::banner::NDSBanner;
+use ndsutils::bootcode::{ARM7Bootcode, ARM9Bootcode};
+use ndsutils::crypto::{decrypt_secure_area, verify_secure_area_magic};
+use ndsutils::header::NDSCartridgeHeader;
+
+use super::{encr_data, open_rom, CommandResult, BAD_DECRYPTION, SECURE_AREA_DISABLED};
+use crate::args::Args;
+
+pub const FLAGS: &[&str] = &["force-decrypt"];
+
+// dump <rom> [--force-decrypt]: writes arm9.bin (with its secure area decrypted), arm7.bin and,
+// if the ROM has a banner, icon.png. --force-decrypt is as for decrypt.
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "rom")?)?;
+    let ndshdr = NDSCartridgeHeader::parse_nds(&mut ndsfile)?;
+
+    let mut arm9code = ARM9Bootcode::new(&mut ndsfile, &ndshdr)?;
+    let arm7code = ARM7Bootcode::new(&mut ndsfile, &ndshdr)?;
+
+    if ndshdr.secure_area_disable() {
+        eprintln!("warning: {}; dumping it as-is", SECURE_AREA_DISABLED);
+    }
+    if arm9code.secure_area_encrypted {
+        let encr = encr_data(args)?;
+        let force = args.flag("force-decrypt");
+        decrypt_secure_area(&mut arm9code, &encr, ndshdr.gamecode, force)
+            .map_err(|_| BAD_DECRYPTION)?;
+        if !verify_secure_area_magic(&arm9code) {
+            eprintln!("warning: {}; dumping the result anyway", BAD_DECRYPTION);
+        }
+    }
+
+    std::fs::write("arm9.bin", &arm9code.raw_data)?;
+    arm7code.dump_to_writer(&mut File::create("arm7.bin")?)?;
+
+    if ndshdr.banner_offset != 0 {
+        NDSBanner::parse(&mut ndsfile, &ndshdr)?.export_icon_png(Path::new("icon.png"))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use ndsutils::bootcode::ARM9Bootcode;
+use ndsutils::crypto::encrypt_secure_area;
+use ndsutils::header::NDSCartridgeHeader;
+
+use super::{encr_data, open_rom, write_rom_with_arm9, CommandResult};
+use crate::args::Args;
+
+pub const FLAGS: &[&str] = &[];
+
+// encrypt <input> <output>: writes a copy of a ROM with a decrypted ARM9 secure area (one
+// starting with E7FFDEFFE7FFDEFFh) re-encrypted.
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "input")?)?;
+    let out_path = args.positional(1, "output")?;
+
+    let mut ndshdr = NDSCartridgeHeader::parse_nds(&mut ndsfile)?;
+    let mut arm9code = ARM9Bootcode::new(&mut ndsfile, &ndshdr)?;
+
+    if !arm9code.secure_area_present {
+        return Err("ROM has no ARM9 secure area".into());
+    }
+    if arm9code.secure_area_encrypted {
+        return Err("ARM9 secure area is already encrypted".into());
+    }
+
+    let encr = encr_data(args)?;
+    encrypt_secure_area(&mut arm9code, &encr, ndshdr.gamecode);
+
+    write_rom_with_arm9(&mut ndsfile, out_path, &mut ndshdr, &arm9code)?;
+
+    println!("Encrypted secure area written to {}", out_path);
+    Ok(())
+}
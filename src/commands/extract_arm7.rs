@@ -0,0 +1,27 @@
+use std::fs::File;
+
+use ndsutils::bootcode::ARM7Bootcode;
+use ndsutils::header::NDSCartridgeHeader;
+
+use super::{open_rom, CommandResult};
+use crate::args::Args;
+
+pub const FLAGS: &[&str] = &["decrypt"];
+
+// extract-arm7 <rom> <output> [--decrypt]: writes the ARM7 bootcode. It is never encrypted, so
+// --decrypt is accepted, for symmetry with extract-arm9, but changes nothing.
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "rom")?)?;
+    let out_path = args.positional(1, "output")?;
+
+    let ndshdr = NDSCartridgeHeader::parse_nds(&mut ndsfile)?;
+    let arm7code = ARM7Bootcode::new(&mut ndsfile, &ndshdr)?;
+
+    if args.flag("decrypt") {
+        eprintln!("note: the ARM7 bootcode is never encrypted; writing it as-is");
+    }
+    arm7code.dump_to_writer(&mut File::create(out_path)?)?;
+
+    println!("ARM7 written to {}", out_path);
+    Ok(())
+}
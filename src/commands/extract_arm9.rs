@@ -0,0 +1,38 @@
+use ndsutils::bootcode::ARM9Bootcode;
+use ndsutils::crypto::{decrypt_secure_area, verify_secure_area_magic};
+use ndsutils::header::NDSCartridgeHeader;
+
+use super::{encr_data, open_rom, CommandResult, BAD_DECRYPTION, SECURE_AREA_DISABLED};
+use crate::args::Args;
+
+pub const FLAGS: &[&str] = &["decrypt", "force-decrypt"];
+
+/* extract-arm9 <rom> <output> [--decrypt] [--force-decrypt]: writes the ARM9 bootcode exactly as
+stored, so with its secure area still encrypted in a retail ROM. --decrypt decrypts it first;
+--force-decrypt is as for decrypt. */
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "rom")?)?;
+    let out_path = args.positional(1, "output")?;
+
+    let ndshdr = NDSCartridgeHeader::parse_nds(&mut ndsfile)?;
+    let mut arm9code = ARM9Bootcode::new(&mut ndsfile, &ndshdr)?;
+
+    if args.flag("decrypt") {
+        if ndshdr.secure_area_disable() {
+            eprintln!("warning: {}; writing it as-is", SECURE_AREA_DISABLED);
+        }
+        if arm9code.secure_area_encrypted {
+            let encr = encr_data(args)?;
+            let force = args.flag("force-decrypt");
+            decrypt_secure_area(&mut arm9code, &encr, ndshdr.gamecode, force)
+                .map_err(|_| BAD_DECRYPTION)?;
+            if !verify_secure_area_magic(&arm9code) {
+                eprintln!("warning: {}; writing the result anyway", BAD_DECRYPTION);
+            }
+        }
+    }
+
+    std::fs::write(out_path, &arm9code.raw_data)?;
+    println!("ARM9 written to {}", out_path);
+    Ok(())
+}
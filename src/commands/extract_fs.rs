@@ -0,0 +1,39 @@
+use std::path::Path;
+
+use ndsutils::filesystem::NitroFS;
+use ndsutils::header::NDSCartridgeHeader;
+
+use super::{open_rom, CommandResult};
+use crate::args::Args;
+use crate::progress::ProgressBar;
+
+pub const FLAGS: &[&str] = &["verbose"];
+
+// extract-fs <rom> <output_dir> [--verbose]: writes every NitroFS file under output_dir.
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "rom")?)?;
+    let out_dir = Path::new(args.positional(1, "output_dir")?);
+
+    let ndshdr = NDSCartridgeHeader::parse_nds(&mut ndsfile)?;
+
+    // Progress goes to stderr: one line per file when verbose, otherwise a progress bar
+    let written = if args.flag("verbose") {
+        let nitro = NitroFS::parse(&mut ndsfile, &ndshdr)?;
+        nitro.extract_to(&mut ndsfile, out_dir, |path, size| {
+            eprintln!("{} ({} bytes)", path.display(), size);
+        })?
+    } else {
+        let bar = ProgressBar::new("Extracting");
+        let written = NitroFS::extract_all(
+            &mut ndsfile,
+            &ndshdr,
+            out_dir,
+            Some(&|done, total| bar.update(done, total)),
+        )?;
+        bar.finish();
+        written
+    };
+
+    eprintln!("Extracted {} files to {}", written, out_dir.display());
+    Ok(())
+}
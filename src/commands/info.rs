@@ -0,0 +1,179 @@
+use std::io::{Seek, SeekFrom};
+
+use ndsutils::banner::{BannerLanguage, NDSBanner};
+use ndsutils::bootcode::{
+    check_secure_area_crc, dump_secure_area_header, is_arm9_compressed, ARM9Bootcode,
+    SecureAreaHeader,
+};
+use ndsutils::crypto::decrypt_secure_area;
+use ndsutils::digest::to_hex;
+use ndsutils::header::NDSCartridgeHeader;
+use ndsutils::overlay::{list_overlays, OverlayTable};
+use ndsutils::rom::{rom_crc32, rom_md5, rom_sha1};
+#[cfg(feature = "rom-db")]
+use ndsutils::romdb::lookup_rom_db;
+
+use super::{
+    encr_data, json_output, ok_bad, open_rom, CommandResult, BAD_DECRYPTION, SECURE_AREA_DISABLED,
+};
+use crate::args::Args;
+use crate::json::Json;
+
+// Every header field, named as in NDSCartridgeHeader but in camelCase. Byte arrays are given as
+// hex strings.
+fn header_json(hdr: &NDSCartridgeHeader) -> Json {
+    // Copies of the unaligned fields of the packed header
+    let (gamecode, makercode) = (hdr.gamecode, hdr.makercode);
+    let (secure_area_crc, secure_area_delay) = (hdr.secure_area_crc, hdr.secure_area_delay);
+    let (logo_crc, header_crc) = (hdr.logo_crc, hdr.header_crc);
+
+    Json::object([
+        ("gametitle", hdr.game_title().into()),
+        ("gamecode", hdr.game_code_display().into()),
+        ("gamecodeRaw", gamecode.into()),
+        ("makercode", hdr.maker_code_str().as_ref().into()),
+        ("makercodeRaw", makercode.into()),
+        ("unitcode", hdr.unitcode[0].into()),
+        ("unitcodeName", hdr.unit_code().to_string().into()),
+        ("encrseedsel", hdr.encrseedsel[0].into()),
+        ("devicecaps", hdr.devicecaps[0].into()),
+        ("ndsregion", hdr.ndsregion[0].into()),
+        ("region", hdr.region().to_string().into()),
+        ("romversion", hdr.romversion[0].into()),
+        ("autostart", hdr.autostart[0].into()),
+        ("arm9off", hdr.arm9off.into()),
+        ("arm9entry", hdr.arm9_entry_address().into()),
+        ("arm9raddr", hdr.arm9_ram_address().into()),
+        ("arm9size", hdr.arm9size.into()),
+        ("arm7off", hdr.arm7off.into()),
+        ("arm7entry", hdr.arm7_entry_address().into()),
+        ("arm7raddr", hdr.arm7_ram_address().into()),
+        ("arm7size", hdr.arm7size.into()),
+        ("fntOffset", hdr.fnt_offset.into()),
+        ("fntSize", hdr.fnt_size.into()),
+        ("fatOffset", hdr.fat_offset.into()),
+        ("fatSize", hdr.fat_size.into()),
+        ("arm9OverlayOffset", hdr.arm9_overlay_offset.into()),
+        ("arm9OverlaySize", hdr.arm9_overlay_size.into()),
+        ("arm7OverlayOffset", hdr.arm7_overlay_offset.into()),
+        ("arm7OverlaySize", hdr.arm7_overlay_size.into()),
+        ("romctrlNormal", hdr.romctrl_normal.into()),
+        ("romctrlKey1", hdr.romctrl_key1.into()),
+        ("bannerOffset", hdr.banner_offset.into()),
+        ("secureAreaCrc", secure_area_crc.into()),
+        ("secureAreaDelay", secure_area_delay.into()),
+        ("arm9AutoloadHook", hdr.arm9_autoload_hook.into()),
+        ("arm7AutoloadHook", hdr.arm7_autoload_hook.into()),
+        ("secureAreaDisable", to_hex(&hdr.secure_area_disable).into()),
+        ("totalUsedRomSize", hdr.total_used_rom_size.into()),
+        ("headerSize", hdr.header_size.into()),
+        ("logo", to_hex(&hdr.logo).into()),
+        ("logoCrc", logo_crc.into()),
+        ("headerCrc", header_crc.into()),
+        ("debugRomOffset", hdr.debug_rom_offset.into()),
+        ("debugSize", hdr.debug_size.into()),
+        ("debugRamAddress", hdr.debug_ram_address.into()),
+    ])
+}
+
+pub const FLAGS: &[&str] = &["sha1", "md5", "verbose", "json"];
+
+// info <rom> [--sha1] [--md5] [--verbose] [--output-format json|text] [--encr-data <path>]
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "rom")?)?;
+    let ndshdr = NDSCartridgeHeader::parse_nds(&mut ndsfile)?;
+
+    if json_output(args)? {
+        println!("{}", header_json(&ndshdr).to_pretty_string());
+        return Ok(());
+    }
+
+    print!("{}", ndshdr);
+    #[cfg(feature = "rom-db")]
+    if let Some(entry) = lookup_rom_db(ndshdr.gamecode, ndshdr.makercode) {
+        println!(
+            "Known as: {} ({}, revision {})",
+            entry.name, entry.region, entry.revision
+        );
+    }
+    println!();
+
+    ndsfile.seek(SeekFrom::Start(0))?;
+    println!("ROM CRC32: {:08x}", rom_crc32(&mut ndsfile)?);
+    if args.flag("sha1") {
+        ndsfile.seek(SeekFrom::Start(0))?;
+        println!("ROM SHA-1: {}", to_hex(&rom_sha1(&mut ndsfile)?));
+    }
+    if args.flag("md5") {
+        ndsfile.seek(SeekFrom::Start(0))?;
+        println!("ROM MD5: {}", to_hex(&rom_md5(&mut ndsfile)?));
+    }
+
+    let header_crc = ndshdr.header_crc;
+    println!(
+        "Header CRC16 from ROM: {:#06x}, actual: {:#06x}... {}",
+        header_crc,
+        ndshdr.compute_header_crc16(),
+        ok_bad(ndshdr.validate_header_crc16())
+    );
+
+    let mut arm9code = ARM9Bootcode::new(&mut ndsfile, &ndshdr)?;
+    if ndshdr.secure_area_disable() {
+        println!("NOTE: {}.", SECURE_AREA_DISABLED);
+    } else if !arm9code.secure_area_present {
+        println!("NOTE: ROM has no ARM9 secure area.");
+    } else {
+        if arm9code.secure_area_encrypted {
+            println!("NOTE: ARM9 secure area requires decryption.");
+
+            let encr = encr_data(args)?;
+            if decrypt_secure_area(&mut arm9code, &encr, ndshdr.gamecode, false).is_err() {
+                println!("NOTE: {}.", BAD_DECRYPTION);
+            }
+        } else {
+            println!("NOTE: ARM9 secure area is already decrypted.");
+        }
+
+        let secure_header = SecureAreaHeader::parse(&arm9code.raw_data);
+        let secure_area = arm9code.raw_data.get(0x10..0x800).unwrap_or_default();
+        match check_secure_area_crc(&secure_header, secure_area) {
+            Ok((crc_ok, crc_actual)) => println!(
+                "Secure area CRC16 from ROM: {:#06x}, actual: {:#06x}... {}",
+                secure_header.crc16,
+                crc_actual,
+                ok_bad(crc_ok)
+            ),
+            Err(e) => println!("Secure area CRC16: {}", e),
+        }
+        if args.flag("verbose") {
+            print!("{}", dump_secure_area_header(&arm9code));
+        }
+    }
+    if is_arm9_compressed(&arm9code, &ndshdr) {
+        println!("NOTE: ARM9 is BLZ-compressed.");
+    }
+    println!();
+
+    match NDSBanner::parse(&mut ndsfile, &ndshdr) {
+        Ok(banner) => {
+            for lang in BannerLanguage::ALL {
+                if banner.has_title(lang) {
+                    let title = banner.title(lang).replace('\n', " / ");
+                    println!("Banner title ({}): {}", lang.name(), title);
+                }
+            }
+        }
+        Err(e) => println!("Banner: {}", e),
+    }
+
+    let arm9_overlays = OverlayTable::parse_arm9(&mut ndsfile, &ndshdr)?;
+    let arm7_overlays = OverlayTable::parse_arm7(&mut ndsfile, &ndshdr)?;
+    println!("ARM9 overlays: {}", arm9_overlays.iter().count());
+    println!("ARM7 overlays: {}", arm7_overlays.iter().count());
+    if args.flag("verbose") && !(arm9_overlays.0.is_empty() && arm7_overlays.0.is_empty()) {
+        println!();
+        list_overlays(&arm9_overlays, &arm7_overlays);
+    }
+
+    Ok(())
+}
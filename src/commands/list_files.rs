@@ -0,0 +1,21 @@
+use std::io::{self, Write};
+
+use ndsutils::filesystem::NitroFNT;
+use ndsutils::header::NDSCartridgeHeader;
+
+use super::{open_rom, CommandResult};
+use crate::args::Args;
+
+pub const FLAGS: &[&str] = &[];
+
+// list-files <rom>: prints every NitroFS file as "{file ID:04X}  {path}", in file ID order.
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "rom")?)?;
+    let ndshdr = NDSCartridgeHeader::parse_nds(&mut ndsfile)?;
+    let fnt = NitroFNT::parse(&mut ndsfile, &ndshdr)?;
+
+    let mut out = io::stdout().lock();
+    fnt.write_listing(&mut out)?;
+    out.flush()?;
+    Ok(())
+}
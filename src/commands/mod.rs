@@ -0,0 +1,149 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+
+use colored::{ColoredString, Colorize};
+use ndsutils::bootcode::{ARM7Bootcode, ARM9Bootcode};
+use ndsutils::crypto::load_encr_data;
+#[cfg(feature = "bundled-encr")]
+use ndsutils::crypto::load_encr_data_embedded;
+use ndsutils::filesystem::NitroFAT;
+use ndsutils::header::NDSCartridgeHeader;
+use ndsutils::rom::create_rom_copy;
+
+use crate::args::Args;
+
+pub mod decrypt;
+pub mod diff;
+pub mod dump;
+pub mod encrypt;
+pub mod extract_arm7;
+pub mod extract_arm9;
+pub mod extract_fs;
+pub mod info;
+pub mod list_files;
+pub mod pack_fs;
+pub mod pad;
+pub mod replace_arm7;
+pub mod replace_arm9;
+pub mod replace_banner;
+pub mod set_region;
+pub mod trim;
+pub mod validate;
+
+pub type CommandResult = Result<(), Box<dyn Error>>;
+
+#[cfg(not(feature = "bundled-encr"))]
+pub const DEFAULT_ENCR_DATA: &str = "encr_data.bin";
+
+// Shown when the secure area ID is wrong after decryption
+pub const BAD_DECRYPTION: &str =
+    "secure area did not decrypt correctly (wrong encr_data.bin, or a corrupt ROM?)";
+
+// Shown instead of decrypting when the header's secure area disable field is set
+pub const SECURE_AREA_DISABLED: &str =
+    "the header disables the secure area (078h is set), so the ARM9 is not encrypted";
+
+pub fn open_rom(path: &str) -> Result<File, Box<dyn Error>> {
+    File::open(path).map_err(|e| format!("cannot open {}: {}", path, e).into())
+}
+
+pub fn load_encr_data_from(path: &Path) -> Result<[u32; 1042], Box<dyn Error>> {
+    let mut encr_data =
+        File::open(path).map_err(|e| format!("cannot open {}: {}", path.display(), e))?;
+    Ok(load_encr_data(&mut encr_data)?)
+}
+
+// The Blowfish key data from --encr-data if given, else the built-in copy (or encr_data.bin in
+// the working directory, when built without the bundled-encr feature).
+pub fn encr_data(args: &Args) -> Result<[u32; 1042], Box<dyn Error>> {
+    match args.option("encr-data") {
+        Some(path) => load_encr_data_from(Path::new(path)),
+        #[cfg(feature = "bundled-encr")]
+        None => Ok(load_encr_data_embedded()),
+        #[cfg(not(feature = "bundled-encr"))]
+        None => load_encr_data_from(Path::new(DEFAULT_ENCR_DATA)),
+    }
+}
+
+// Whether --output-format json (or the older --json flag) was given; the default is text, and an
+// explicit --output-format text wins over --json.
+pub fn json_output(args: &Args) -> Result<bool, Box<dyn Error>> {
+    match args.option("output-format") {
+        Some("json") => Ok(true),
+        Some("text") => Ok(false),
+        Some(other) => {
+            Err(format!("unknown output format '{}' (expected json or text)", other).into())
+        }
+        None => Ok(args.flag("json")),
+    }
+}
+
+pub fn ok_bad(ok: bool) -> ColoredString {
+    if ok {
+        "OK".green()
+    } else {
+        "BAD".red()
+    }
+}
+
+// Writes a copy of `src` to `out_path` with the ARM9 replaced, then rewrites the header so its
+// CRC16s match.
+pub fn write_rom_with_arm9(
+    src: &mut File,
+    out_path: &str,
+    hdr: &mut NDSCartridgeHeader,
+    arm9: &ARM9Bootcode,
+) -> CommandResult {
+    let mut out = create_rom_copy(src, out_path)?;
+
+    arm9.write_to(&mut out, hdr, false)?;
+    hdr.write_to(&mut out)?;
+
+    Ok(())
+}
+
+// As write_rom_with_arm9, for the ARM7.
+pub fn write_rom_with_arm7(
+    src: &mut File,
+    out_path: &str,
+    hdr: &mut NDSCartridgeHeader,
+    arm7: &ARM7Bootcode,
+) -> CommandResult {
+    let mut out = create_rom_copy(src, out_path)?;
+
+    out.seek(SeekFrom::Start(hdr.arm7off as u64))?;
+    out.write_all(&arm7.raw_data)?;
+    hdr.write_to(&mut out)?;
+
+    Ok(())
+}
+
+// The lowest offset past `start` of any section or NitroFS file, which bounds how far the
+// section at `start` can grow.
+pub fn next_section_offset(
+    nds: &mut File,
+    hdr: &NDSCartridgeHeader,
+    start: u32,
+) -> Result<Option<u64>, Box<dyn Error>> {
+    let mut offsets = vec![
+        hdr.arm9off,
+        hdr.arm7off,
+        hdr.fnt_offset,
+        hdr.fat_offset,
+        hdr.arm9_overlay_offset,
+        hdr.arm7_overlay_offset,
+        hdr.banner_offset,
+    ];
+    if hdr.fat_size != 0 {
+        let fat = NitroFAT::parse(nds, hdr)?;
+        offsets.extend(fat.0.iter().filter(|e| !e.is_empty()).map(|e| e.start));
+    }
+
+    Ok(offsets
+        .into_iter()
+        .filter(|&offset| offset > start)
+        .min()
+        .map(|offset| offset as u64))
+}
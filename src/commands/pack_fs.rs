@@ -0,0 +1,64 @@
+use std::fs;
+use std::path::Path;
+
+use ndsutils::filesystem::{read_entry, NitroFS};
+use ndsutils::header::NDSCartridgeHeader;
+use ndsutils::rom::create_rom_file;
+
+use super::{open_rom, CommandResult};
+use crate::args::Args;
+use crate::progress::ProgressBar;
+
+pub const FLAGS: &[&str] = &["verbose"];
+
+// pack-fs <original> <replacement_dir> <output>: rebuilds a ROM with any file found under
+// replacement_dir (at its extracted path) substituted for the original.
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "original")?)?;
+    let replacement_dir = Path::new(args.positional(1, "replacement_dir")?);
+    let out_path = args.positional(2, "output")?;
+
+    let mut ndshdr = NDSCartridgeHeader::parse_nds(&mut ndsfile)?;
+    let mut nitro = NitroFS::parse(&mut ndsfile, &ndshdr)?;
+
+    let mut replacements: Vec<(u16, Vec<u8>)> = vec![];
+    for (path, file_id) in nitro.fnt.iter() {
+        let replacement_path = replacement_dir.join(&path);
+        if !replacement_path.is_file() {
+            continue;
+        }
+
+        let data = fs::read(&replacement_path)?;
+        let entry = nitro
+            .fat
+            .0
+            .get(file_id as usize)
+            .ok_or_else(|| format!("file ID {} is not in the FAT", file_id))?;
+        if read_entry(&mut ndsfile, entry)? != data {
+            if args.flag("verbose") {
+                eprintln!("{} ({} bytes)", path.display(), data.len());
+            }
+            replacements.push((file_id, data));
+        }
+    }
+
+    // repack copies the ROM itself
+    let mut out = create_rom_file(out_path)?;
+    let bar = ProgressBar::new("Packing");
+    let relocated = nitro.repack(
+        &mut ndsfile,
+        &mut out,
+        &mut ndshdr,
+        &replacements,
+        Some(&|done, total| bar.update(done, total)),
+    )?;
+    bar.finish();
+
+    eprintln!(
+        "Replaced {} files ({} relocated) in {}",
+        replacements.len(),
+        relocated,
+        out_path
+    );
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use ndsutils::rom::pad_rom;
+
+use super::{open_rom, CommandResult};
+use crate::args::Args;
+use crate::progress::ProgressBar;
+
+pub const FLAGS: &[&str] = &[];
+
+// pad <input> <output> [--size <bytes>]: writes a copy of the ROM padded with FFh to the given
+// power-of-two size, or to the next power of two.
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "input")?)?;
+    let out_path = args.positional(1, "output")?;
+    let target_size = match args.option("size") {
+        Some(size) => Some(parse_size(size)?),
+        None => None,
+    };
+
+    let rom_len = ndsfile.metadata()?.len();
+    let mut out = BufWriter::new(File::create(out_path)?);
+    let bar = ProgressBar::new("Padding");
+    let written = pad_rom(
+        &mut ndsfile,
+        &mut out,
+        target_size,
+        Some(&|done, total| bar.update(done, total)),
+    )?;
+    out.flush()?;
+    bar.finish();
+
+    println!(
+        "Padded {:#x} bytes; {:#x} bytes written to {}",
+        written - rom_len,
+        written,
+        out_path
+    );
+    Ok(())
+}
+
+// A byte count, in decimal or with a 0x prefix in hex.
+fn parse_size(size: &str) -> Result<u64, String> {
+    let parsed = match size.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => size.parse(),
+    };
+    parsed.map_err(|_| format!("--size expects a byte count, got '{}'", size))
+}
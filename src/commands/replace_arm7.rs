@@ -0,0 +1,58 @@
+use std::fs;
+
+use ndsutils::bootcode::ARM7Bootcode;
+use ndsutils::header::NDSCartridgeHeader;
+
+use super::{next_section_offset, open_rom, write_rom_with_arm7, CommandResult};
+use crate::args::Args;
+
+pub const FLAGS: &[&str] = &["allow-resize"];
+
+// replace-arm7 <input> <arm7> <output> [--allow-resize]: as replace-arm9, for the ARM7
+// bootcode.
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "input")?)?;
+    let arm7_path = args.positional(1, "arm7")?;
+    let out_path = args.positional(2, "output")?;
+
+    let mut ndshdr = NDSCartridgeHeader::parse_nds(&mut ndsfile)?;
+    let data = fs::read(arm7_path).map_err(|e| format!("cannot open {}: {}", arm7_path, e))?;
+
+    let arm7off = ndshdr.arm7off;
+    let arm7size = ndshdr.arm7size;
+    let rom_len = ndsfile.metadata()?.len();
+    if data.len() as u64 > arm7size as u64 {
+        if !args.flag("allow-resize") {
+            return Err(format!(
+                "{} is {:#x} bytes, larger than the original ARM7 ({:#x}); pass --allow-resize",
+                arm7_path,
+                data.len(),
+                arm7size
+            )
+            .into());
+        }
+
+        let limit = next_section_offset(&mut ndsfile, &ndshdr, arm7off)?.unwrap_or(rom_len);
+        if arm7off as u64 + data.len() as u64 > limit {
+            return Err(format!(
+                "{} is {:#x} bytes, but only {:#x} are free at the ARM7 offset",
+                arm7_path,
+                data.len(),
+                limit - arm7off as u64
+            )
+            .into());
+        }
+    }
+
+    ndshdr.arm7size = data.len() as u32;
+    let arm7_end = arm7off + ndshdr.arm7size;
+    if arm7_end > ndshdr.total_used_rom_size {
+        ndshdr.total_used_rom_size = arm7_end;
+    }
+
+    let arm7code = ARM7Bootcode { raw_data: data };
+    write_rom_with_arm7(&mut ndsfile, out_path, &mut ndshdr, &arm7code)?;
+
+    println!("ARM7 written to {}", out_path);
+    Ok(())
+}
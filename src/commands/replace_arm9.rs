@@ -0,0 +1,64 @@
+use std::fs;
+
+use ndsutils::bootcode::ARM9Bootcode;
+use ndsutils::header::NDSCartridgeHeader;
+
+use super::{next_section_offset, open_rom, write_rom_with_arm9, CommandResult};
+use crate::args::Args;
+
+pub const FLAGS: &[&str] = &["allow-resize"];
+
+// replace-arm9 <input> <arm9> <output> [--allow-resize]: writes a copy of the ROM with the
+// ARM9 bootcode replaced by a raw binary. Unless --allow-resize is given, the new binary may
+// not be larger than the original.
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "input")?)?;
+    let arm9_path = args.positional(1, "arm9")?;
+    let out_path = args.positional(2, "output")?;
+
+    let mut ndshdr = NDSCartridgeHeader::parse_nds(&mut ndsfile)?;
+    let data = fs::read(arm9_path).map_err(|e| format!("cannot open {}: {}", arm9_path, e))?;
+
+    let arm9off = ndshdr.arm9off;
+    let arm9size = ndshdr.arm9size;
+    let rom_len = ndsfile.metadata()?.len();
+    if data.len() as u64 > arm9size as u64 {
+        if !args.flag("allow-resize") {
+            return Err(format!(
+                "{} is {:#x} bytes, larger than the original ARM9 ({:#x}); pass --allow-resize",
+                arm9_path,
+                data.len(),
+                arm9size
+            )
+            .into());
+        }
+
+        // Only the gap up to whatever is stored next can be taken
+        let limit = next_section_offset(&mut ndsfile, &ndshdr, arm9off)?.unwrap_or(rom_len);
+        if arm9off as u64 + data.len() as u64 > limit {
+            return Err(format!(
+                "{} is {:#x} bytes, but only {:#x} are free at the ARM9 offset",
+                arm9_path,
+                data.len(),
+                limit - arm9off as u64
+            )
+            .into());
+        }
+    }
+
+    ndshdr.arm9size = data.len() as u32;
+    let arm9_end = arm9off + ndshdr.arm9size;
+    if arm9_end > ndshdr.total_used_rom_size {
+        ndshdr.total_used_rom_size = arm9_end;
+    }
+
+    let arm9code = ARM9Bootcode {
+        raw_data: data,
+        secure_area_present: (0x4000..0x8000).contains(&arm9off),
+        secure_area_encrypted: false,
+    };
+    write_rom_with_arm9(&mut ndsfile, out_path, &mut ndshdr, &arm9code)?;
+
+    println!("ARM9 written to {}", out_path);
+    Ok(())
+}
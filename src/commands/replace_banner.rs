@@ -0,0 +1,60 @@
+use std::fs;
+use std::io::{Seek, SeekFrom, Write};
+
+use ndsutils::banner::{banner_size, NDSBanner};
+use ndsutils::header::NDSCartridgeHeader;
+use ndsutils::rom::create_rom_copy;
+
+use super::{open_rom, CommandResult};
+use crate::args::Args;
+
+pub const FLAGS: &[&str] = &[];
+
+// replace-banner <input> <banner> <output>: writes a copy of the ROM with its banner replaced
+// by a raw banner blob, fixing up the blob's CRC16s if they don't match.
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "input")?)?;
+    let banner_path = args.positional(1, "banner")?;
+    let out_path = args.positional(2, "output")?;
+
+    let ndshdr = NDSCartridgeHeader::parse_nds(&mut ndsfile)?;
+    if ndshdr.banner_offset == 0 {
+        return Err("ROM has no banner to replace".into());
+    }
+    let old_banner = NDSBanner::parse(&mut ndsfile, &ndshdr)?;
+
+    let blob = fs::read(banner_path).map_err(|e| format!("cannot open {}: {}", banner_path, e))?;
+    let mut banner = NDSBanner::from_bytes(&blob)?;
+    let size = banner_size(banner.version) as usize;
+    if blob.len() < size {
+        return Err(format!(
+            "{} is {:#x} bytes, but a version {:#06x} banner needs {:#x}",
+            banner_path,
+            blob.len(),
+            banner.version,
+            size
+        )
+        .into());
+    }
+    // Don't spill over whatever follows the old banner
+    if size > banner_size(old_banner.version) as usize {
+        return Err(format!(
+            "version {:#06x} banner is larger than the ROM's version {:#06x} banner",
+            banner.version, old_banner.version
+        )
+        .into());
+    }
+
+    if !banner.validate_crc() {
+        eprintln!("warning: banner CRC16 mismatch, recomputing");
+    }
+
+    let mut out = create_rom_copy(&mut ndsfile, out_path)?;
+
+    out.seek(SeekFrom::Start(ndshdr.banner_offset as u64))?;
+    out.write_all(&blob[..size])?;
+    banner.write_to(&mut out, &ndshdr)?;
+
+    println!("Banner written to {}", out_path);
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use ndsutils::bootcode::ARM9Bootcode;
+use ndsutils::crypto::{decrypt_secure_area, encrypt_secure_area};
+use ndsutils::header::{NDSCartridgeHeader, NdsRegion};
+
+use super::{encr_data, open_rom, write_rom_with_arm9, CommandResult, BAD_DECRYPTION};
+use crate::args::Args;
+
+fn parse_region(name: &str) -> Result<NdsRegion, String> {
+    match name.to_ascii_uppercase().as_str() {
+        "JP" | "JPN" | "JAPAN" => Ok(NdsRegion::Japan),
+        "US" | "USA" => Ok(NdsRegion::Usa),
+        "EU" | "EUR" | "EUROPE" => Ok(NdsRegion::Europe),
+        "AU" | "AUS" | "AUSTRALIA" => Ok(NdsRegion::Australia),
+        "CN" | "CHN" | "CHINA" => Ok(NdsRegion::China),
+        "KR" | "KOR" | "KOREA" => Ok(NdsRegion::Korea),
+        _ => Err(format!(
+            "unknown region '{}' (expected JP, US, EU, AU, CN or KR)",
+            name
+        )),
+    }
+}
+
+pub const FLAGS: &[&str] = &[];
+
+/* set-region <input> <region> <output>: writes a copy of the ROM retargeted at another region,
+as NDSCartridgeHeader::set_region describes. The new gamecode changes the KEY1 keys, so an
+encrypted secure area is decrypted with the old gamecode and re-encrypted with the new one. */
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "input")?)?;
+    let region = parse_region(args.positional(1, "region")?)?;
+    let out_path = args.positional(2, "output")?;
+
+    let mut ndshdr = NDSCartridgeHeader::parse_nds(&mut ndsfile)?;
+    let mut arm9code = ARM9Bootcode::new(&mut ndsfile, &ndshdr)?;
+    let old_gamecode = ndshdr.gamecode;
+    ndshdr.set_region(region)?;
+
+    if arm9code.secure_area_encrypted && ndshdr.gamecode != old_gamecode {
+        let encr = encr_data(args)?;
+        decrypt_secure_area(&mut arm9code, &encr, old_gamecode, false)
+            .map_err(|_| BAD_DECRYPTION)?;
+        encrypt_secure_area(&mut arm9code, &encr, ndshdr.gamecode);
+    }
+
+    write_rom_with_arm9(&mut ndsfile, out_path, &mut ndshdr, &arm9code)?;
+
+    println!(
+        "ROM retargeted at {} ({}) written to {}",
+        region,
+        ndshdr.game_code_display(),
+        out_path
+    );
+    Ok(())
+}
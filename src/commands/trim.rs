@@ -0,0 +1,35 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use ndsutils::rom::trim_rom;
+
+use super::{open_rom, CommandResult};
+use crate::args::Args;
+use crate::progress::ProgressBar;
+
+pub const FLAGS: &[&str] = &[];
+
+// trim <input> <output>: writes a copy of the ROM without its trailing FFh padding.
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "input")?)?;
+    let out_path = args.positional(1, "output")?;
+
+    let rom_len = ndsfile.metadata()?.len();
+    let mut out = BufWriter::new(File::create(out_path)?);
+    let bar = ProgressBar::new("Trimming");
+    let written = trim_rom(
+        &mut ndsfile,
+        &mut out,
+        Some(&|done, total| bar.update(done, total)),
+    )?;
+    out.flush()?;
+    bar.finish();
+
+    println!(
+        "Trimmed {:#x} bytes; {:#x} bytes written to {}",
+        rom_len - written,
+        written,
+        out_path
+    );
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use ndsutils::validate::{validate_rom, ValidationCheck};
+
+use super::{json_output, ok_bad, open_rom, CommandResult};
+use crate::args::Args;
+use crate::json::Json;
+
+// One check as {check, passed, expected, actual}, always in that order.
+fn check_json(check: &ValidationCheck) -> Json {
+    Json::object([
+        ("check", check.name.as_str().into()),
+        ("passed", check.passed.into()),
+        ("expected", check.expected.as_str().into()),
+        ("actual", check.actual.as_str().into()),
+    ])
+}
+
+pub const FLAGS: &[&str] = &["json"];
+
+// validate <rom> [--output-format json|text]: prints a pass/fail table of every check, failing
+// if any check failed.
+pub fn run(args: &Args) -> CommandResult {
+    let mut ndsfile = open_rom(args.positional(0, "rom")?)?;
+    let checks = validate_rom(&mut ndsfile)?;
+
+    if json_output(args)? {
+        let json = Json::Array(checks.iter().map(check_json).collect());
+        println!("{}", json.to_pretty_string());
+    } else {
+        for check in &checks {
+            println!(
+                "{:<28} {:<4} expected {}, got {}",
+                check.name,
+                ok_bad(check.passed),
+                check.expected,
+                check.actual
+            );
+        }
+    }
+
+    let failed = checks.iter().filter(|c| !c.passed).count();
+    if failed > 0 {
+        return Err(format!("{} of {} checks failed", failed, checks.len()).into());
+    }
+    Ok(())
+}
@@ -2,7 +2,8 @@
 // NDS BIOS CRC function
 // ----------------------
 
-static MODBUSCRC16LUT: [u16; 256] = [
+// The BIOS's table, as found in the BIOS itself; generate_crc16_table recreates it.
+const MODBUSCRC16LUT: [u16; 256] = [
     0x0000, 0xC0C1, 0xC181, 0x0140, 0xC301, 0x03C0, 0x0280, 0xC241, 0xC601, 0x06C0, 0x0780, 0xC741,
     0x0500, 0xC5C1, 0xC481, 0x0440, 0xCC01, 0x0CC0, 0x0D80, 0xCD41, 0x0F00, 0xCFC1, 0xCE81, 0x0E40,
     0x0A00, 0xCAC1, 0xCB81, 0x0B40, 0xC901, 0x09C0, 0x0880, 0xC841, 0xD801, 0x18C0, 0x1980, 0xD941,
@@ -27,14 +28,230 @@ static MODBUSCRC16LUT: [u16; 256] = [
     0x4100, 0x81C1, 0x8081, 0x4040,
 ];
 
+/// Builds the lookup table behind [`bios_get_crc16`].
+///
+/// The polynomial is 0x8005 (x^16 + x^15 + x^2 + 1), processed least significant bit first as
+/// the BIOS does, which amounts to shifting right and XORing with its bit-reversed form, 0xA001.
+/// Entry `i` is the CRC register after feeding the 8 bits of `i` through from a zero start.
+///
+/// ```
+/// use ndsutils::crc::generate_crc16_table;
+///
+/// let table = generate_crc16_table();
+/// assert_eq!(table[1], 0xC0C1);
+/// assert_eq!(table[0x80], 0xA001);
+/// ```
+pub const fn generate_crc16_table() -> [u16; 256] {
+    let mut lut = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u16;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        lut[i] = crc;
+        i += 1;
+    }
+    lut
+}
+
+// Fails the build if the generator and the BIOS table ever disagree
+const _: () = {
+    let generated = generate_crc16_table();
+    let mut i = 0;
+    while i < 256 {
+        assert!(generated[i] == MODBUSCRC16LUT[i]);
+        i += 1;
+    }
+};
+
+/// Computes the CRC16 the NDS BIOS uses for the header, logo, secure area and banner checksums.
+///
+/// This is CRC-16/MODBUS: the reflected 0x8005 polynomial (0xA001) with an initial value of
+/// 0xFFFF and no final XOR, so an empty slice gives 0xFFFF.
+///
+/// ```
+/// use ndsutils::crc::bios_get_crc16;
+///
+/// assert_eq!(bios_get_crc16(&[]), 0xFFFF);
+/// assert_eq!(bios_get_crc16(&[0u8; 16]), 0xF0BE);
+/// assert_eq!(bios_get_crc16(b"123456789"), 0x4B37);
+/// ```
+///
+/// The secure area header at 4000h stores, at 400Eh, the CRC of the 7F0h bytes following it.
+/// For an unused, zero-filled secure area:
+///
+/// ```
+/// use ndsutils::bootcode::{check_secure_area_crc, SecureAreaHeader};
+/// use ndsutils::crc::bios_get_crc16;
+///
+/// let mut secure_area = vec![0u8; 0x800];
+/// secure_area[0xE..0x10].copy_from_slice(&0xFFAAu16.to_le_bytes());
+/// assert_eq!(bios_get_crc16(&secure_area[0x10..]), 0xFFAA);
+///
+/// let header = SecureAreaHeader::parse(&secure_area);
+/// assert!(check_secure_area_crc(&header, &secure_area[0x10..]).unwrap().0);
+/// assert!(check_secure_area_crc(&header, &secure_area[0x20..]).is_err());
+/// ```
 pub fn bios_get_crc16(data: &[u8]) -> u16 {
+    let mut hasher = Crc16Hasher::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Computes the same CRC16 as [`bios_get_crc16`], over data fed in as many pieces as needed.
+///
+/// This lets a CRC be taken over a section as it is read or written, without holding all of it
+/// in memory. It is also a [`core::hash::Hasher`], whose `finish` gives the CRC widened to u64,
+/// and, with the `std` feature, an [`std::io::Write`] sink, so `io::copy` can feed it.
+///
+/// ```
+/// use ndsutils::crc::{bios_get_crc16, Crc16Hasher};
+///
+/// let mut hasher = Crc16Hasher::new();
+/// hasher.update(b"1234");
+/// hasher.update(b"56789");
+/// assert_eq!(hasher.finalize(), bios_get_crc16(b"123456789"));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Crc16Hasher {
+    crc: u16,
+}
+
+impl Crc16Hasher {
+    pub const fn new() -> Self {
+        Crc16Hasher { crc: 0xFFFF }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        let mut temp: u8;
+
+        for b in data {
+            temp = b ^ self.crc as u8;
+            self.crc >>= 8;
+            self.crc ^= MODBUSCRC16LUT[temp as usize];
+        }
+    }
+
+    /// The CRC of everything fed in so far; more can still be added afterwards.
+    pub fn finalize(&self) -> u16 {
+        self.crc
+    }
+}
+
+impl Default for Crc16Hasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::hash::Hasher for Crc16Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.finalize() as u64
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Write for Crc16Hasher {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// -------------------------------
+// CRC32 (ISO-HDLC, as used by zip)
+// -------------------------------
+
+const fn make_crc32_lut() -> [u32; 256] {
+    // Reflected form of polynomial 04C11DB7h
+    let mut lut = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        lut[i] = crc;
+        i += 1;
+    }
+    lut
+}
+
+static CRC32LUT: [u32; 256] = make_crc32_lut();
+
+// Continues a CRC32 over more data. Start with a crc of 0; the result of each call can be fed
+// straight back in.
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc_out = !crc;
+
+    for b in data {
+        crc_out = (crc_out >> 8) ^ CRC32LUT[((crc_out as u8) ^ b) as usize];
+    }
+
+    !crc_out
+}
+
+// CRC32 of a whole buffer: CRC-32/ISO-HDLC, the reflected polynomial 04C11DB7h with an initial
+// value and final XOR of FFFFFFFFh. This is the CRC used by zip, PNG and ROM databases.
+pub fn crc32_iso(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+// ------------------------------------------
+// CRC16 (CCITT-FALSE, as used by some tools)
+// ------------------------------------------
+
+const fn make_crc16_ccitt_lut() -> [u16; 256] {
+    // Polynomial 1021h, not reflected
+    let mut lut = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        lut[i] = crc;
+        i += 1;
+    }
+    lut
+}
+
+static CRC16CCITTLUT: [u16; 256] = make_crc16_ccitt_lut();
+
+// CRC-16/CCITT-FALSE: polynomial 1021h, initial value FFFFh, no reflection or final XOR.
+// Unrelated to the BIOS CRC16 despite the shared width.
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
     let mut crc_out: u16 = 0xFFFF;
-    let mut temp: u8;
 
     for b in data {
-        temp = b ^ crc_out as u8;
-        crc_out >>= 8;
-        crc_out ^= MODBUSCRC16LUT[temp as usize];
+        crc_out = (crc_out << 8) ^ CRC16CCITTLUT[((crc_out >> 8) as u8 ^ b) as usize];
     }
 
     crc_out
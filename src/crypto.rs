@@ -0,0 +1,345 @@
+/* Only the key schedule and the cipher itself (blowfish_nds, apply_keycode, init_keycode) are
+available without the std feature; reading key files and working on ARM9 bootcode need std. */
+
+use core::mem::size_of_val;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "std")]
+use std::mem::{align_of, size_of};
+
+#[cfg(feature = "std")]
+use byteorder::{LittleEndian, ReadBytesExt};
+
+#[cfg(feature = "std")]
+use crate::bootcode::{ARM9Bootcode, SecureAreaHeader, SECURE_AREA_ID, SECURE_AREA_ID_DECRYPTED};
+#[cfg(feature = "std")]
+use crate::crc::bios_get_crc16;
+#[cfg(feature = "std")]
+use crate::error::NdsError;
+
+// Views 64-bit words as their bytes, in native byte order.
+pub fn u64_slice_as_bytes(s: &[u64]) -> &[u8] {
+    // u8 has no alignment requirement and every byte of a u64 is initialised
+    unsafe { core::slice::from_raw_parts(s.as_ptr() as *const u8, size_of_val(s)) }
+}
+
+// Views bytes as 64-bit words, in native byte order. The slice must be 8-byte aligned and
+// a whole number of words long.
+#[cfg(feature = "std")]
+pub fn bytes_as_u64_slice(s: &[u8]) -> Result<&[u64], NdsError> {
+    if !(s.as_ptr() as usize).is_multiple_of(align_of::<u64>()) {
+        return Err(NdsError::InvalidInput(
+            "buffer is not 8-byte aligned".to_string(),
+        ));
+    }
+    if !s.len().is_multiple_of(size_of::<u64>()) {
+        return Err(NdsError::InvalidInput(format!(
+            "buffer length {:#x} is not a multiple of 8",
+            s.len()
+        )));
+    }
+
+    // Alignment and length are checked above, and any bit pattern is a valid u64
+    Ok(unsafe { std::slice::from_raw_parts(s.as_ptr() as *const u64, s.len() / size_of::<u64>()) })
+}
+
+pub fn blowfish_nds(v: &mut u64, kbuf: &[u32], enc: bool) {
+    let mut y: u32 = *v as u32;
+    let mut x: u32 = (*v >> 32) as u32;
+    let mut z: u32;
+
+    for round in 0..16 {
+        // P-array[0..15] in order when encrypting, P-array[17..2] when decrypting
+        let i = if enc { round } else { 17 - round };
+        z = kbuf[i] ^ x; // P-array XOR
+        x = kbuf[(0x12 + ((z >> 24) & 0xFF)) as usize]; // S-box[0]
+        x = kbuf[(0x112 + ((z >> 16) & 0xFF)) as usize].wrapping_add(x); // S-box[1]
+        x ^= kbuf[(0x212 + ((z >> 8) & 0xFF)) as usize]; // S-box[2]
+        x = kbuf[(0x312 + (z & 0xFF)) as usize].wrapping_add(x); // S-box[3]
+        x ^= y;
+        y = z;
+    }
+
+    // P-array[16,17] XOR (or P-array[1,0] when decrypting)
+    if enc {
+        x ^= kbuf[16];
+        y ^= kbuf[17];
+    } else {
+        x ^= kbuf[1];
+        y ^= kbuf[0];
+    }
+    *v = (x as u64) | ((y as u64) << 32);
+}
+
+/* Applies a series of arbitrary manipulations on the P-array and S-boxes
+   depending on the title key.
+*/
+pub fn apply_keycode(tk: &mut [u32; 3], kbuf: &mut [u32]) {
+    // The two encrypt steps overlap each other: first over tk[1..3], then over tk[0..2]
+    for lo in [1, 0] {
+        let mut combined = (tk[lo] as u64) | ((tk[lo + 1] as u64) << 32);
+        blowfish_nds(&mut combined, kbuf, true);
+        tk[lo] = combined as u32;
+        tk[lo + 1] = (combined >> 32) as u32;
+    }
+    let mut scratch: u64 = 0;
+
+    // The P-array is XORed with the (byte-swapped) keycode, modulo 8 bytes
+    for i in 0..0x12 {
+        kbuf[i] ^= tk[i % 2].swap_bytes();
+    }
+
+    // Then the whole key buffer is regenerated by repeatedly encrypting a scratch value
+    for i in (0..0x412).step_by(2) {
+        blowfish_nds(&mut scratch, kbuf, true);
+        kbuf[i] = (scratch >> 32) as u32;
+        kbuf[i + 1] = scratch as u32;
+    }
+}
+
+// How many apply_keycode steps a KEY1 key buffer is built with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum KeyLevel {
+    Level1 = 1, // The cartridge's KEY1 commands
+    Level2,     // The secure area ID
+    Level3,     // The rest of the secure area
+}
+
+/* Builds the KEY1 key buffer for the given level from the contents of encr_data.bin, as in
+GBATEK's init_keycode. */
+pub fn init_keycode(encr: &[u32; 1042], idcode: u32, level: KeyLevel) -> [u32; 1042] {
+    init_keycode_traced(encr, idcode, level, |_, _| {})
+}
+
+// As init_keycode, calling `trace` with the step number (from 1) and the key buffer after
+// each apply_keycode step.
+pub fn init_keycode_traced(
+    encr: &[u32; 1042],
+    idcode: u32,
+    level: KeyLevel,
+    mut trace: impl FnMut(u8, &[u32]),
+) -> [u32; 1042] {
+    let mut kbuf = *encr;
+    let mut keycode: [u32; 3] = [idcode, idcode >> 1, idcode << 1];
+
+    apply_keycode(&mut keycode, &mut kbuf);
+    trace(1, &kbuf);
+    if level >= KeyLevel::Level2 {
+        apply_keycode(&mut keycode, &mut kbuf);
+        trace(2, &kbuf);
+    }
+    keycode[1] <<= 1;
+    keycode[2] >>= 1;
+    if level >= KeyLevel::Level3 {
+        apply_keycode(&mut keycode, &mut kbuf);
+        trace(3, &kbuf);
+    }
+
+    kbuf
+}
+
+/* The level 3 KEY1 key buffer for one game, which the whole 2K secure area is encrypted with.
+Each game's key is seeded from its own gamecode, so it has to be built per ROM; the secure area
+ID is encrypted once more with the level 2 key, which decrypt_secure_area takes care of. */
+pub struct SecureAreaKey(pub [u32; 1042]);
+
+impl SecureAreaKey {
+    // `encr_data` must hold the contents of encr_data.bin.
+    pub fn for_gamecode(gamecode: u32, encr_data: &[u32; 1042]) -> Self {
+        SecureAreaKey(init_keycode(encr_data, gamecode, KeyLevel::Level3))
+    }
+
+    pub fn encrypt(&self, block: &mut u64) {
+        blowfish_nds(block, &self.0, true);
+    }
+
+    pub fn decrypt(&self, block: &mut u64) {
+        blowfish_nds(block, &self.0, false);
+    }
+}
+
+/* Writes a key buffer as hex, 8 words to a line, each line starting with the index of its first
+word: the P-array (0..17) and then each S-box (18..1041) under its own label. */
+#[cfg(feature = "std")]
+pub fn dump_kbuf(kbuf: &[u32], out: &mut dyn Write) -> io::Result<()> {
+    let sections = [
+        ("P-array", 0..0x12),
+        ("S-box 0", 0x12..0x112),
+        ("S-box 1", 0x112..0x212),
+        ("S-box 2", 0x212..0x312),
+        ("S-box 3", 0x312..0x412),
+    ];
+    for (label, range) in sections {
+        writeln!(out, "{}:", label)?;
+        let words = kbuf.get(range.clone()).unwrap_or_default();
+        for (i, line) in words.chunks(8).enumerate() {
+            write!(out, "  {:03x}:", range.start + i * 8)?;
+            for word in line {
+                write!(out, " {:08x}", word)?;
+            }
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+// The KEY2 seed bytes, indexed by the header's encryption seed select, per GBATEK.
+pub const KEY2_SEEDS: [u8; 8] = [0xE8, 0x4D, 0x5A, 0xB1, 0x17, 0x8F, 0x99, 0xD5];
+
+// Looks up the KEY2 seed byte, rejecting seed selects past the end of the table rather than
+// wrapping around to a wrong key.
+#[cfg(feature = "std")]
+pub fn key2_seed(seed_select: u8) -> Result<u8, NdsError> {
+    KEY2_SEEDS
+        .get(seed_select as usize)
+        .copied()
+        .ok_or_else(|| {
+            NdsError::UnsupportedFormat(format!("encryption seed select {:#04x}", seed_select))
+        })
+}
+
+// The size of encr_data.bin: the 18-word P-array followed by four 256-word S-boxes.
+pub const ENCR_DATA_SIZE: u64 = 1042 * 4;
+
+// The first words of the P-array in the genuine encr_data.bin, as dumped from the BIOS.
+#[cfg(feature = "std")]
+const ENCR_DATA_P_ARRAY_START: [u32; 4] = [0x5F20D599, 0xB9F54457, 0xD9A4196E, 0x945A6A9E];
+
+/* Reads encr_data.bin. The file must be exactly 1048h bytes and start with the known P-array
+values, so that a wrong file is caught here rather than producing garbage keys later. */
+#[cfg(feature = "std")]
+pub fn load_encr_data<R: Read + Seek>(encr_data: &mut R) -> Result<[u32; 1042], NdsError> {
+    let len = encr_data.seek(SeekFrom::End(0))?;
+    if len != ENCR_DATA_SIZE {
+        return Err(NdsError::InvalidEncrData(format!(
+            "expected a {} byte file, got {} bytes",
+            ENCR_DATA_SIZE, len
+        )));
+    }
+    encr_data.seek(SeekFrom::Start(0))?;
+
+    let mut contents: [u32; 1042] = [0; 1042];
+    encr_data.read_u32_into::<LittleEndian>(&mut contents)?;
+
+    if contents[..4] != ENCR_DATA_P_ARRAY_START {
+        return Err(NdsError::InvalidEncrData(format!(
+            "expected the P-array to start {:08X} {:08X} {:08X} {:08X}",
+            ENCR_DATA_P_ARRAY_START[0],
+            ENCR_DATA_P_ARRAY_START[1],
+            ENCR_DATA_P_ARRAY_START[2],
+            ENCR_DATA_P_ARRAY_START[3]
+        )));
+    }
+
+    Ok(contents)
+}
+
+#[cfg(feature = "bundled-encr")]
+static ENCR_DATA: &[u8; ENCR_DATA_SIZE as usize] = include_bytes!("../encr_data.bin");
+
+// The contents of encr_data.bin as built into the crate.
+#[cfg(feature = "bundled-encr")]
+pub fn load_encr_data_embedded() -> [u32; 1042] {
+    let mut contents: [u32; 1042] = [0; 1042];
+    for (word, bytes) in contents.iter_mut().zip(ENCR_DATA.chunks_exact(4)) {
+        *word = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+    contents
+}
+
+// Runs blowfish_nds over one 8-byte block of a byte buffer.
+#[cfg(feature = "std")]
+fn crypt_block(block: &mut [u8], kbuf: &[u32], enc: bool) {
+    let mut v = u64::from_le_bytes(block[..8].try_into().unwrap());
+    blowfish_nds(&mut v, kbuf, enc);
+    block[..8].copy_from_slice(&v.to_le_bytes());
+}
+
+/* Decrypts the 2K ARM9 secure area in-place, following the same steps as the firmware:
+the whole area is encrypted with the level 3 key, and the first 8 bytes (the secure
+area ID) are additionally encrypted with the level 2 key.
+`encr` holds the contents of encr_data.bin, and the level 3 key derived from it is returned.
+If the secure area ID doesn't come out right, which means the wrong encr_data.bin or gamecode
+was used, the bootcode is left untouched and InvalidMagic returned.
+With `force`, the result is kept anyway, for ROMs whose secure area doesn't start with the usual
+ID; check verify_secure_area_magic afterwards, as with the wrong keys the output is garbage. */
+#[cfg(feature = "std")]
+pub fn decrypt_secure_area(
+    arm9: &mut ARM9Bootcode,
+    encr: &[u32; 1042],
+    gamecode: u32,
+    force: bool,
+) -> Result<SecureAreaKey, NdsError> {
+    let key = SecureAreaKey::for_gamecode(gamecode, encr);
+    if !arm9.secure_area_present || !arm9.secure_area_encrypted {
+        return Ok(key);
+    }
+
+    let mut secure_area = arm9.raw_data[..0x800].to_vec();
+    let level2 = init_keycode(encr, gamecode, KeyLevel::Level2);
+    crypt_block(&mut secure_area[..8], &level2, false);
+
+    for block in secure_area.chunks_exact_mut(8) {
+        crypt_block(block, &key.0, false);
+    }
+
+    // Decrypted dumps conventionally replace the "encryObj" ID with the undefined-instruction
+    // pattern, which is also how already-decrypted ROMs are recognised.
+    if secure_area[..8] == SECURE_AREA_ID.to_le_bytes() {
+        secure_area[..8].copy_from_slice(&SECURE_AREA_ID_DECRYPTED.to_le_bytes());
+    }
+
+    let decrypted = ARM9Bootcode {
+        raw_data: secure_area,
+        secure_area_present: true,
+        secure_area_encrypted: false,
+    };
+    if !force && !verify_secure_area_magic(&decrypted) {
+        return Err(NdsError::InvalidMagic);
+    }
+
+    arm9.raw_data[..0x800].copy_from_slice(&decrypted.raw_data);
+    arm9.secure_area_encrypted = false;
+    Ok(key)
+}
+
+// Whether the secure area starts with the ID a correctly decrypted dump has.
+#[cfg(feature = "std")]
+pub fn verify_secure_area_magic(arm9: &ARM9Bootcode) -> bool {
+    arm9.secure_area_id() == Some(SECURE_AREA_ID_DECRYPTED.to_le_bytes())
+}
+
+/* The exact inverse of decrypt_secure_area, used when packing a ROM with a plaintext secure area.
+The CRC16 at [secure_area+0Eh] is recomputed first: it lives inside the encrypted area and
+covers the plaintext of [secure_area+10h..secure_area+800h]. As there, `encr` is only read and
+the level 3 key is returned. */
+#[cfg(feature = "std")]
+pub fn encrypt_secure_area(
+    arm9: &mut ARM9Bootcode,
+    encr: &[u32; 1042],
+    gamecode: u32,
+) -> SecureAreaKey {
+    let key = SecureAreaKey::for_gamecode(gamecode, encr);
+    if !arm9.secure_area_present || arm9.secure_area_encrypted {
+        return key;
+    }
+
+    if arm9.raw_data[..8] == SECURE_AREA_ID_DECRYPTED.to_le_bytes() {
+        arm9.raw_data[..8].copy_from_slice(&SECURE_AREA_ID.to_le_bytes());
+    }
+
+    let mut header = SecureAreaHeader::parse(&arm9.raw_data);
+    header.crc16 = bios_get_crc16(&arm9.raw_data[0x10..0x800]);
+    arm9.raw_data[..SecureAreaHeader::SIZE].copy_from_slice(&header.as_bytes());
+
+    let level2 = init_keycode(encr, gamecode, KeyLevel::Level2);
+    for block in arm9.raw_data[..0x800].chunks_exact_mut(8) {
+        crypt_block(block, &key.0, true);
+    }
+
+    crypt_block(&mut arm9.raw_data[..8], &level2, true);
+
+    arm9.secure_area_encrypted = true;
+    key
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum NdsError {
+    Io(std::io::Error),
+    InvalidMagic,
+    BadCrc,
+    EncryptionFailed,
+    UnsupportedFormat(String),
+    InvalidInput(String),
+    InvalidEncrData(String),
+    FileTooSmall { expected: u64, got: u64 },
+    BufferTooSmall { expected: usize, got: usize },
+    SizeChanged { expected: u64, got: u64 },
+    FileTooLarge { expected: u64, got: u64 },
+}
+
+pub type Result<T> = std::result::Result<T, NdsError>;
+
+impl fmt::Display for NdsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NdsError::Io(e) => write!(f, "I/O error: {}", e),
+            NdsError::InvalidMagic => write!(f, "Invalid magic value"),
+            NdsError::BadCrc => write!(f, "CRC mismatch"),
+            NdsError::EncryptionFailed => write!(f, "Encryption failed"),
+            NdsError::UnsupportedFormat(what) => write!(f, "Unsupported format: {}", what),
+            NdsError::InvalidInput(what) => write!(f, "Invalid input: {}", what),
+            NdsError::FileTooSmall { expected, got } => write!(
+                f,
+                "File too small: expected at least {:#x} bytes, got {:#x}",
+                expected, got
+            ),
+            NdsError::BufferTooSmall { expected, got } => write!(
+                f,
+                "Buffer too small: expected at least {:#x} bytes, got {:#x}",
+                expected, got
+            ),
+            NdsError::SizeChanged { expected, got } => write!(
+                f,
+                "Size changed: expected at most {:#x} bytes, got {:#x}",
+                expected, got
+            ),
+            NdsError::FileTooLarge { expected, got } => write!(
+                f,
+                "File too large: expected at most {:#x} bytes, got {:#x}",
+                expected, got
+            ),
+            NdsError::InvalidEncrData(what) => {
+                write!(f, "Not a valid encr_data.bin ({})", what)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NdsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NdsError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for NdsError {
+    fn from(e: std::io::Error) -> Self {
+        NdsError::Io(e)
+    }
+}
@@ -0,0 +1,1073 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::ops::Sub;
+use std::path::{Component, Path, PathBuf};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::banner::banner_size;
+use crate::error::{NdsError, Result};
+use crate::header::NDSCartridgeHeader;
+
+// Directory IDs in the FNT are offset by F000h, the root being F000h itself.
+pub const ROOT_DIR_ID: u16 = 0xF000;
+
+// One directory of the File Name Table, with its sub-table already decoded.
+pub struct NitroDirEntry {
+    pub name: String, // Empty for the root directory
+    pub parent_id: u16,
+    pub first_file_id: u16,
+    pub files: Vec<(String, u16)>,
+    pub subdirs: Vec<u16>,
+}
+
+// The NitroFS File Name Table, as a flat list of directories indexed by (ID - F000h).
+pub struct NitroFNT {
+    pub dirs: Vec<NitroDirEntry>,
+}
+
+impl NitroFNT {
+    pub fn parse<R: Read + Seek>(nds: &mut R, hdr: &NDSCartridgeHeader) -> Result<NitroFNT> {
+        if hdr.fnt_size == 0 {
+            return Ok(NitroFNT { dirs: vec![] });
+        }
+
+        let mut raw = vec![0u8; hdr.fnt_size as usize];
+        nds.seek(SeekFrom::Start(hdr.fnt_offset as u64))?;
+        nds.read_exact(&mut raw)?;
+        let mut fnt = Cursor::new(&raw[..]);
+
+        // The root's entry in the main table stores the total number of directories
+        // in place of its parent ID.
+        fnt.seek(SeekFrom::Start(6))?;
+        let dir_count = fnt.read_u16::<LittleEndian>()? as usize;
+        fnt.seek(SeekFrom::Start(0))?;
+        if dir_count == 0 || dir_count > 0x1000 || dir_count * 8 > raw.len() {
+            return Err(NdsError::UnsupportedFormat(format!(
+                "FNT directory count {}",
+                dir_count
+            )));
+        }
+
+        let mut dirs: Vec<NitroDirEntry> = Vec::with_capacity(dir_count);
+        let mut subtable_offsets: Vec<u32> = Vec::with_capacity(dir_count);
+        for i in 0..dir_count {
+            subtable_offsets.push(fnt.read_u32::<LittleEndian>()?);
+            let first_file_id = fnt.read_u16::<LittleEndian>()?;
+            let parent_id = fnt.read_u16::<LittleEndian>()?;
+
+            dirs.push(NitroDirEntry {
+                name: String::new(),
+                parent_id: if i == 0 { ROOT_DIR_ID } else { parent_id },
+                first_file_id,
+                files: vec![],
+                subdirs: vec![],
+            });
+        }
+
+        // Sub-tables: a sequence of length-prefixed names. Files are numbered consecutively
+        // from first_file_id, and subdirectory names are only stored in their parent.
+        for i in 0..dir_count {
+            fnt.seek(SeekFrom::Start(subtable_offsets[i] as u64))?;
+            let mut file_id = dirs[i].first_file_id;
+            loop {
+                let typelen = fnt.read_u8()?;
+                if typelen == 0x00 {
+                    break;
+                } else if typelen == 0x80 {
+                    return Err(NdsError::UnsupportedFormat(
+                        "reserved FNT entry type 80h".to_string(),
+                    ));
+                }
+
+                let mut name = vec![0u8; (typelen & 0x7F) as usize];
+                fnt.read_exact(&mut name)?;
+                let name = String::from_utf8_lossy(&name).into_owned();
+
+                if typelen & 0x80 == 0 {
+                    dirs[i].files.push((name, file_id));
+                    file_id = file_id.wrapping_add(1);
+                } else {
+                    let dir_id = fnt.read_u16::<LittleEndian>()?;
+                    let idx = dir_id.wrapping_sub(ROOT_DIR_ID) as usize;
+                    if idx == 0 || idx >= dir_count {
+                        return Err(NdsError::UnsupportedFormat(format!(
+                            "FNT directory ID {:#06x} out of range",
+                            dir_id
+                        )));
+                    }
+                    dirs[idx].name = name;
+                    dirs[i].subdirs.push(dir_id);
+                }
+            }
+        }
+
+        Ok(NitroFNT { dirs })
+    }
+
+    /* Builds a table holding the given files, numbered from first_file_id. Directories are
+    created as the paths need them and get their IDs depth-first; within each directory files
+    are numbered in name order. Paths are relative to the root and use '/' separators. */
+    pub fn from_paths(paths: &[&str], first_file_id: u16) -> Result<NitroFNT> {
+        let mut sorted: Vec<Vec<&str>> = paths.iter().map(|p| p.split('/').collect()).collect();
+        sorted.sort();
+
+        let mut dirs = vec![NitroDirEntry {
+            name: String::new(),
+            parent_id: ROOT_DIR_ID,
+            first_file_id,
+            files: vec![],
+            subdirs: vec![],
+        }];
+        for components in &sorted {
+            check_path(components)?;
+
+            let (file_name, dir_names) = components.split_last().unwrap();
+            let mut idx = 0;
+            for &dir_name in dir_names {
+                let existing = dirs[idx]
+                    .subdirs
+                    .iter()
+                    .copied()
+                    .find(|&id| dirs[id.wrapping_sub(ROOT_DIR_ID) as usize].name == dir_name);
+                idx = match existing {
+                    Some(id) => (id - ROOT_DIR_ID) as usize,
+                    None => {
+                        if dirs.len() >= 0x1000 {
+                            return Err(NdsError::InvalidInput(
+                                "too many NitroFS directories".to_string(),
+                            ));
+                        }
+                        let id = ROOT_DIR_ID + dirs.len() as u16;
+                        dirs[idx].subdirs.push(id);
+                        dirs.push(NitroDirEntry {
+                            name: dir_name.to_string(),
+                            parent_id: ROOT_DIR_ID + idx as u16,
+                            first_file_id: 0,
+                            files: vec![],
+                            subdirs: vec![],
+                        });
+                        dirs.len() - 1
+                    }
+                };
+            }
+            if dirs[idx].files.iter().any(|(name, _)| name == file_name) {
+                return Err(NdsError::InvalidInput(format!(
+                    "duplicate NitroFS path {}",
+                    components.join("/")
+                )));
+            }
+            dirs[idx].files.push((file_name.to_string(), 0));
+        }
+
+        let mut file_id = first_file_id as u32;
+        for dir in &mut dirs {
+            dir.first_file_id = file_id as u16;
+            for (_, id) in &mut dir.files {
+                *id = file_id as u16;
+                file_id += 1;
+            }
+        }
+        if file_id > ROOT_DIR_ID as u32 {
+            return Err(NdsError::InvalidInput("too many NitroFS files".to_string()));
+        }
+
+        Ok(NitroFNT { dirs })
+    }
+
+    /* Serialises the table: the main table of 8-byte directory entries, then each directory's
+    sub-table, files first and then subdirectories. */
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut subtables: Vec<u8> = vec![];
+        let mut main: Vec<u8> = Vec::with_capacity(self.dirs.len() * 8);
+        let main_len = self.dirs.len() * 8;
+
+        for (i, dir) in self.dirs.iter().enumerate() {
+            main.extend_from_slice(&((main_len + subtables.len()) as u32).to_le_bytes());
+            main.extend_from_slice(&dir.first_file_id.to_le_bytes());
+            // The root stores the directory count in place of its parent
+            let parent = if i == 0 {
+                self.dirs.len() as u16
+            } else {
+                dir.parent_id
+            };
+            main.extend_from_slice(&parent.to_le_bytes());
+
+            for (name, _) in &dir.files {
+                subtables.push(name.len() as u8);
+                subtables.extend_from_slice(name.as_bytes());
+            }
+            for &dir_id in &dir.subdirs {
+                let name = &self.dirs[dir_id.wrapping_sub(ROOT_DIR_ID) as usize].name;
+                subtables.push(0x80 | name.len() as u8);
+                subtables.extend_from_slice(name.as_bytes());
+                subtables.extend_from_slice(&dir_id.to_le_bytes());
+            }
+            subtables.push(0);
+        }
+
+        main.extend_from_slice(&subtables);
+        main
+    }
+
+    // Path of a directory relative to the NitroFS root.
+    pub fn dir_path(&self, dir_id: u16) -> PathBuf {
+        let mut components: Vec<&str> = vec![];
+        let mut idx = dir_id.wrapping_sub(ROOT_DIR_ID) as usize;
+
+        // Bounded by the directory count, so a malformed parent chain can't loop forever
+        for _ in 0..self.dirs.len() {
+            if idx == 0 || idx >= self.dirs.len() {
+                break;
+            }
+            components.push(&self.dirs[idx].name);
+            idx = self.dirs[idx].parent_id.wrapping_sub(ROOT_DIR_ID) as usize;
+        }
+
+        components.iter().rev().collect()
+    }
+
+    /* The ID of the file at `path`, relative to the root (a leading '/' is allowed). Names are
+    matched exactly, as the table stores them. */
+    pub fn find_by_path(&self, path: &Path) -> Option<u16> {
+        let mut names = path
+            .components()
+            .filter(|c| !matches!(c, Component::RootDir | Component::CurDir))
+            .map(|c| match c {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            });
+        let file_name = names.next_back()??;
+
+        let mut dir = self.dirs.first()?;
+        for name in names {
+            let name = name?;
+            dir = dir
+                .subdirs
+                .iter()
+                .filter_map(|&id| self.dirs.get(id.wrapping_sub(ROOT_DIR_ID) as usize))
+                .find(|subdir| subdir.name == name)?;
+        }
+
+        dir.files
+            .iter()
+            .find(|(name, _)| name == file_name)
+            .map(|&(_, id)| id)
+    }
+
+    /* Adds a file to the table, creating any directories it needs, and returns its ID. A
+    directory's files must have consecutive IDs, so the new file takes the ID after the last one
+    in its directory and every later file ID moves up by one; any FAT or overlay table has to be
+    shifted to match. New directories are added after every existing one, so a file in a new
+    directory gets the ID after all the others. */
+    pub fn add_file(&mut self, path: &str) -> Result<u16> {
+        let components: Vec<&str> = path.split('/').collect();
+        check_path(&components)?;
+        let (file_name, dir_names) = components.split_last().unwrap();
+        if self.dirs.is_empty() {
+            return Err(NdsError::InvalidInput(
+                "the FNT has no root directory".to_string(),
+            ));
+        }
+
+        // One past the highest file ID: where a new directory's files start
+        let end_id = self
+            .dirs
+            .iter()
+            .map(|dir| dir.first_file_id as u32 + dir.files.len() as u32)
+            .max()
+            .unwrap();
+
+        let mut idx = 0;
+        for &dir_name in dir_names {
+            let existing = self.dirs[idx]
+                .subdirs
+                .iter()
+                .copied()
+                .find(|&id| self.dirs[id.wrapping_sub(ROOT_DIR_ID) as usize].name == dir_name);
+            idx = match existing {
+                Some(id) => id.wrapping_sub(ROOT_DIR_ID) as usize,
+                None => {
+                    if self.dirs.len() >= 0x1000 {
+                        return Err(NdsError::InvalidInput(
+                            "too many NitroFS directories".to_string(),
+                        ));
+                    }
+                    let id = ROOT_DIR_ID + self.dirs.len() as u16;
+                    self.dirs[idx].subdirs.push(id);
+                    self.dirs.push(NitroDirEntry {
+                        name: dir_name.to_string(),
+                        parent_id: ROOT_DIR_ID + idx as u16,
+                        first_file_id: end_id as u16,
+                        files: vec![],
+                        subdirs: vec![],
+                    });
+                    self.dirs.len() - 1
+                }
+            };
+        }
+        if self.dirs[idx]
+            .files
+            .iter()
+            .any(|(name, _)| name == file_name)
+        {
+            return Err(NdsError::InvalidInput(format!(
+                "duplicate NitroFS path {}",
+                path
+            )));
+        }
+        if end_id >= ROOT_DIR_ID as u32 {
+            return Err(NdsError::InvalidInput("too many NitroFS files".to_string()));
+        }
+
+        let new_id = self.dirs[idx].first_file_id + self.dirs[idx].files.len() as u16;
+        for (i, dir) in self.dirs.iter_mut().enumerate() {
+            // An empty directory numbered at new_id only moves if it comes after this one
+            if i != idx && (dir.first_file_id > new_id || (dir.first_file_id == new_id && i > idx))
+            {
+                dir.first_file_id += 1;
+            }
+            for (_, id) in &mut dir.files {
+                if *id >= new_id {
+                    *id += 1;
+                }
+            }
+        }
+        self.dirs[idx].files.push((file_name.to_string(), new_id));
+
+        Ok(new_id)
+    }
+
+    // Iterates over every file in the table as (path, file ID) pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (PathBuf, u16)> + '_ {
+        self.dirs.iter().enumerate().flat_map(move |(i, dir)| {
+            let dir_path = self.dir_path(ROOT_DIR_ID + i as u16);
+            dir.files
+                .iter()
+                .map(move |(name, id)| (dir_path.join(name), *id))
+        })
+    }
+
+    // Writes one "{file ID:04X}  {path}" line per file, in file ID order, with '/' separators.
+    pub fn write_listing<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut files: Vec<(PathBuf, u16)> = self.iter().collect();
+        files.sort_by_key(|&(_, id)| id);
+        for (path, id) in files {
+            let components: Vec<_> = path.iter().map(|c| c.to_string_lossy()).collect();
+            writeln!(w, "{:04X}  {}", id, components.join("/"))?;
+        }
+        Ok(())
+    }
+
+    /* Reads back a listing made by write_listing. The directories are numbered in the order
+    their first file appears, and empty directories, not being listed, are lost; otherwise this
+    gives the same paths and IDs. Each directory's files must have consecutive IDs, as the FNT
+    can't express anything else. */
+    pub fn from_listing<R: Read>(r: &mut R) -> Result<NitroFNT> {
+        let mut text = String::new();
+        r.read_to_string(&mut text)?;
+
+        let mut files: Vec<(u16, &str)> = vec![];
+        for (n, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let bad_line =
+                || NdsError::InvalidInput(format!("bad listing line {}: {}", n + 1, line));
+            let (id, path) = line.split_once("  ").ok_or_else(bad_line)?;
+            let id = u16::from_str_radix(id, 16).map_err(|_| bad_line())?;
+            if id >= ROOT_DIR_ID {
+                return Err(bad_line());
+            }
+            files.push((id, path));
+        }
+        files.sort_by_key(|&(id, _)| id);
+
+        // Directories without files of their own start at the ID of the file that created them
+        let mut fnt = NitroFNT {
+            dirs: vec![NitroDirEntry {
+                name: String::new(),
+                parent_id: ROOT_DIR_ID,
+                first_file_id: files.first().map_or(0, |&(id, _)| id),
+                files: vec![],
+                subdirs: vec![],
+            }],
+        };
+        let mut dir_ids: BTreeMap<&str, usize> = BTreeMap::new();
+        for (i, &(id, path)) in files.iter().enumerate() {
+            if i > 0 && files[i - 1].0 == id {
+                return Err(NdsError::InvalidInput(format!(
+                    "file ID {:04X} is listed twice",
+                    id
+                )));
+            }
+            let components: Vec<&str> = path.split('/').collect();
+            check_path(&components)?;
+            let (file_name, dir_names) = components.split_last().unwrap();
+
+            let mut idx = 0;
+            let mut end = 0;
+            for dir_name in dir_names {
+                end += dir_name.len();
+                idx = match dir_ids.get(&path[..end]) {
+                    Some(&existing) => existing,
+                    None => {
+                        if fnt.dirs.len() >= 0x1000 {
+                            return Err(NdsError::InvalidInput(
+                                "too many NitroFS directories".to_string(),
+                            ));
+                        }
+                        let dir_id = ROOT_DIR_ID + fnt.dirs.len() as u16;
+                        fnt.dirs[idx].subdirs.push(dir_id);
+                        fnt.dirs.push(NitroDirEntry {
+                            name: dir_name.to_string(),
+                            parent_id: ROOT_DIR_ID + idx as u16,
+                            first_file_id: id,
+                            files: vec![],
+                            subdirs: vec![],
+                        });
+                        dir_ids.insert(&path[..end], fnt.dirs.len() - 1);
+                        fnt.dirs.len() - 1
+                    }
+                };
+                end += 1;
+            }
+
+            let dir = &mut fnt.dirs[idx];
+            if dir.files.iter().any(|(name, _)| name == file_name) {
+                return Err(NdsError::InvalidInput(format!(
+                    "duplicate NitroFS path {}",
+                    path
+                )));
+            }
+            if dir.files.is_empty() {
+                dir.first_file_id = id;
+            } else if id != dir.first_file_id + dir.files.len() as u16 {
+                return Err(NdsError::InvalidInput(format!(
+                    "file ID {:04X} ({}) doesn't follow on from the rest of its directory",
+                    id, path
+                )));
+            }
+            dir.files.push((file_name.to_string(), id));
+        }
+
+        Ok(fnt)
+    }
+}
+
+// Rejects empty, "." and ".." components, and names too long for an FNT entry.
+fn check_path(components: &[&str]) -> Result<()> {
+    match components
+        .iter()
+        .find(|c| c.is_empty() || c.len() > 0x7F || matches!(**c, "." | ".."))
+    {
+        Some(bad) => Err(NdsError::InvalidInput(format!(
+            "bad NitroFS path component {:?} in {}",
+            bad,
+            components.join("/")
+        ))),
+        None => Ok(()),
+    }
+}
+
+/* An offset into the ROM image. The NDS FAT stores them as u32, while DSi-era storage can need
+u64; either way everything is converted to u64 before seeking. */
+pub trait RomOffset: Copy + Default + Ord + Sub<Output = Self> + Into<u64> {}
+
+impl RomOffset for u32 {}
+impl RomOffset for u64 {}
+
+// One File Allocation Table entry: the [start, end) ROM offsets of a file.
+#[derive(Clone, Copy)]
+pub struct FATEntry<O: RomOffset = u32> {
+    pub start: O,
+    pub end: O,
+}
+
+impl<O: RomOffset> FATEntry<O> {
+    pub fn len(&self) -> O {
+        if self.end > self.start {
+            self.end - self.start
+        } else {
+            O::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == O::default()
+    }
+}
+
+// The NitroFS File Allocation Table, indexed by file ID. As stored in an NDS ROM it is a
+// NitroFAT<u32>; only parse and write_to are tied to that layout.
+pub struct NitroFAT<O: RomOffset = u32>(pub Vec<FATEntry<O>>);
+
+impl NitroFAT<u32> {
+    pub fn parse<R: Read + Seek>(nds: &mut R, hdr: &NDSCartridgeHeader) -> Result<NitroFAT> {
+        nds.seek(SeekFrom::Start(hdr.fat_offset as u64))?;
+
+        let mut entries: Vec<FATEntry> = Vec::with_capacity(hdr.fat_size as usize / 8);
+        for _ in 0..hdr.fat_size / 8 {
+            let start = nds.read_u32::<LittleEndian>()?;
+            let end = nds.read_u32::<LittleEndian>()?;
+            if end < start {
+                return Err(NdsError::UnsupportedFormat(format!(
+                    "FAT entry {} ends before it starts",
+                    entries.len()
+                )));
+            }
+            entries.push(FATEntry { start, end });
+        }
+
+        Ok(NitroFAT(entries))
+    }
+}
+
+impl<O: RomOffset> NitroFAT<O> {
+    // Reads the contents of the file with the given ID.
+    pub fn read_file_by_id<R: Read + Seek>(&self, id: u16, r: &mut R) -> Result<Vec<u8>> {
+        let entry = self
+            .0
+            .get(id as usize)
+            .ok_or_else(|| NdsError::InvalidInput(format!("file ID {} is not in the FAT", id)))?;
+        read_entry(r, entry)
+    }
+}
+
+impl NitroFAT<u32> {
+    // Writes the table back at fat_offset. The entry count must not have changed.
+    pub fn write_to<W: Write + Seek>(&self, w: &mut W, hdr: &NDSCartridgeHeader) -> Result<()> {
+        if self.0.len() * 8 != hdr.fat_size as usize {
+            return Err(NdsError::InvalidInput(format!(
+                "FAT has {} entries but the header has room for {}",
+                self.0.len(),
+                hdr.fat_size / 8
+            )));
+        }
+
+        w.seek(SeekFrom::Start(hdr.fat_offset as u64))?;
+        for entry in &self.0 {
+            w.write_u32::<LittleEndian>(entry.start)?;
+            w.write_u32::<LittleEndian>(entry.end)?;
+        }
+
+        Ok(())
+    }
+}
+
+/* Overwrites a file's contents within its current FAT slot, without moving anything else in
+the image. A shorter replacement is padded with zeroes to the slot's size: as the FAT isn't
+touched, the file keeps its old length and the padding becomes part of it. Anything longer than
+the slot fails with FileTooLarge; use NitroFS::repack to relocate it instead. */
+pub fn replace_file_in_place<RW: Read + Write + Seek, O: RomOffset>(
+    rw: &mut RW,
+    fat: &NitroFAT<O>,
+    file_id: u16,
+    new_data: &[u8],
+) -> Result<()> {
+    let entry = fat
+        .0
+        .get(file_id as usize)
+        .ok_or_else(|| NdsError::InvalidInput(format!("file ID {} is not in the FAT", file_id)))?;
+    let len: u64 = entry.len().into();
+    if new_data.len() as u64 > len {
+        return Err(NdsError::FileTooLarge {
+            expected: len,
+            got: new_data.len() as u64,
+        });
+    }
+
+    rw.seek(SeekFrom::Start(entry.start.into()))?;
+    rw.write_all(new_data)?;
+    rw.write_all(&vec![0; len as usize - new_data.len()])?;
+    Ok(())
+}
+
+/* Adds a file to a ROM image held in memory and returns its file ID. Nothing already in the
+image is moved: the file data, then the rebuilt FNT and FAT, are appended at the end, each on a
+200h boundary with FFh padding, and the header is pointed at the new tables. The old FNT and FAT
+are left behind as unused space. As add_file can shift later file IDs up by one, so are the
+entries of the FAT and of both overlay tables. */
+pub fn inject_file(
+    rom: &mut Vec<u8>,
+    hdr: &mut NDSCartridgeHeader,
+    fat: &mut NitroFAT,
+    fnt: &mut NitroFNT,
+    path: &str,
+    data: &[u8],
+) -> Result<u16> {
+    let file_id = fnt.add_file(path)?;
+    if file_id as usize > fat.0.len() {
+        return Err(NdsError::InvalidInput(format!(
+            "FNT gave file ID {}, but the FAT only has {} entries",
+            file_id,
+            fat.0.len()
+        )));
+    }
+
+    for (offset, size) in [
+        (hdr.arm9_overlay_offset, hdr.arm9_overlay_size),
+        (hdr.arm7_overlay_offset, hdr.arm7_overlay_size),
+    ] {
+        for entry in 0..size as usize / 32 {
+            let field = offset as usize + entry * 32 + 0x18;
+            let Some(bytes) = rom.get_mut(field..field + 4) else {
+                break;
+            };
+            let id = u32::from_le_bytes(bytes.try_into().unwrap());
+            if id >= file_id as u32 {
+                bytes.copy_from_slice(&(id + 1).to_le_bytes());
+            }
+        }
+    }
+
+    let start = append_aligned(rom, data)?;
+    fat.0.insert(
+        file_id as usize,
+        FATEntry {
+            start,
+            end: start + data.len() as u32,
+        },
+    );
+
+    let fnt_bytes = fnt.to_bytes();
+    hdr.fnt_offset = append_aligned(rom, &fnt_bytes)?;
+    hdr.fnt_size = fnt_bytes.len() as u32;
+    hdr.fat_offset = append_aligned(rom, &vec![0; fat.0.len() * 8])?;
+    hdr.fat_size = (fat.0.len() * 8) as u32;
+
+    hdr.total_used_rom_size = rom.len() as u32;
+    let mut capacity = hdr.devicecaps[0];
+    while (0x20000u64 << capacity) < rom.len() as u64 {
+        capacity += 1;
+    }
+    hdr.devicecaps = [capacity];
+
+    let mut cursor = Cursor::new(rom);
+    fat.write_to(&mut cursor, hdr)?;
+    hdr.write_to(&mut cursor)?;
+
+    Ok(file_id)
+}
+
+/* Deletes a file from the NitroFS by marking its FAT entry unused, as {0, 0}. No other file or
+overlay ever changes ID: the FAT keeps its length, and the file's data is left where it was, so
+this leaves a hole in the ROM image that only a compaction pass can reclaim. Its name is removed
+from the FNT when it is the first or last file of its directory. A directory's IDs must stay
+consecutive, though, so a file between two others keeps its name, as an empty file. */
+pub fn delete_file(fat: &mut NitroFAT, fnt: &mut NitroFNT, file_id: u16) -> Result<()> {
+    let not_found = || NdsError::InvalidInput(format!("file ID {} is not in the FNT", file_id));
+    let dir = fnt
+        .dirs
+        .iter_mut()
+        .find(|dir| dir.files.iter().any(|&(_, id)| id == file_id))
+        .ok_or_else(not_found)?;
+    let last_id = dir.first_file_id as usize + dir.files.len() - 1;
+    if last_id >= fat.0.len() {
+        return Err(NdsError::InvalidInput(format!(
+            "FNT has file ID {}, but the FAT only has {} entries",
+            last_id,
+            fat.0.len()
+        )));
+    }
+
+    let pos = dir.files.iter().position(|&(_, id)| id == file_id).unwrap();
+    if pos == 0 {
+        // The rest of the directory keeps its IDs if it starts one later
+        dir.files.remove(pos);
+        dir.first_file_id += 1;
+    } else if pos == dir.files.len() - 1 {
+        dir.files.pop();
+    }
+    fat.0[file_id as usize] = FATEntry { start: 0, end: 0 };
+
+    Ok(())
+}
+
+/* Copies a ROM, read from its start, with the file data rewritten back to back so that the gaps
+left by delete_file or by shrinking replacements are dropped. Everything up to the end of the
+last fixed section (header, ARM9, ARM7, overlay tables and banner, plus the FNT and FAT if they
+come before it) is copied verbatim, apart from the FAT entries and the header, which are
+updated; files in there, such as overlays placed before the ARM7, stay where they are. What
+follows (the remaining files, and an FNT or FAT moved past them as inject_file does) is then laid
+out on successive 200h boundaries in the order it was in. Anything after that isn't copied.
+Returns the header as written, with its used ROM size, FNT and FAT offsets and CRC updated. */
+pub fn compact_nitro_fs<R: Read, W: Write>(
+    src_rom: &mut R,
+    dst_rom: &mut W,
+    hdr: &NDSCartridgeHeader,
+    fat: &NitroFAT,
+) -> Result<NDSCartridgeHeader> {
+    let section_end = |offset: u32, size: u32| match size {
+        0 => 0,
+        _ => offset as u64 + size as u64,
+    };
+    let mut fixed_sections: Vec<(&str, u32, u64)> = [
+        ("header", 0, size_of::<NDSCartridgeHeader>() as u32),
+        ("ARM9", hdr.arm9off, hdr.arm9size),
+        ("ARM7", hdr.arm7off, hdr.arm7size),
+        (
+            "ARM9 overlay table",
+            hdr.arm9_overlay_offset,
+            hdr.arm9_overlay_size,
+        ),
+        (
+            "ARM7 overlay table",
+            hdr.arm7_overlay_offset,
+            hdr.arm7_overlay_size,
+        ),
+    ]
+    .into_iter()
+    .map(|(name, offset, size)| (name, offset, section_end(offset, size)))
+    .collect();
+    let mut area_start = fixed_sections.iter().map(|s| s.2).max().unwrap();
+
+    // The banner's size depends on its version, so that has to be read first
+    let mut prefix = vec![];
+    let read_prefix = |prefix: &mut Vec<u8>, src_rom: &mut R, len: u64| -> Result<()> {
+        let missing = len.saturating_sub(prefix.len() as u64);
+        src_rom.take(missing).read_to_end(prefix)?;
+        if (prefix.len() as u64) < len {
+            return Err(NdsError::FileTooSmall {
+                expected: len,
+                got: prefix.len() as u64,
+            });
+        }
+        Ok(())
+    };
+    let banner_len = if hdr.banner_offset != 0 {
+        let version_end = hdr.banner_offset as u64 + 2;
+        read_prefix(&mut prefix, src_rom, area_start.max(version_end))?;
+        let version = &prefix[hdr.banner_offset as usize..version_end as usize];
+        banner_size(u16::from_le_bytes([version[0], version[1]]))
+    } else {
+        0
+    };
+    let banner_end = section_end(hdr.banner_offset, banner_len);
+    fixed_sections.push(("banner", hdr.banner_offset, banner_end));
+    area_start = area_start.max(banner_end);
+
+    // The FNT and FAT either come before the files or, after inject_file, are moved with them
+    let fat_size = fat.0.len() as u32 * 8;
+    let mut pieces: Vec<(u32, u32, Piece)> = vec![];
+    for (piece, offset, size) in [
+        (Piece::Fnt, hdr.fnt_offset, hdr.fnt_size),
+        (Piece::Fat, hdr.fat_offset, fat_size),
+    ] {
+        let end = section_end(offset, size);
+        if end > area_start && (offset as u64) < area_start {
+            return Err(NdsError::UnsupportedFormat(format!(
+                "{} at {:#x} runs into the fixed sections ending at {:#x}",
+                piece.name(),
+                offset,
+                area_start
+            )));
+        } else if end > area_start {
+            pieces.push((offset, end as u32, piece));
+        } else {
+            fixed_sections.push((piece.name(), offset, end));
+            area_start = area_start.max(end);
+        }
+    }
+    read_prefix(&mut prefix, src_rom, area_start)?;
+
+    let mut new_fat = NitroFAT(vec![FATEntry { start: 0, end: 0 }; fat.0.len()]);
+    for (i, entry) in fat.0.iter().enumerate() {
+        if entry.start == 0 && entry.end == 0 {
+            continue;
+        }
+        if (entry.start as u64) < area_start {
+            if entry.end as u64 > area_start {
+                return Err(NdsError::UnsupportedFormat(format!(
+                    "FAT entry {} runs into the file data at {:#x}",
+                    i, area_start
+                )));
+            }
+            let overlapped = fixed_sections
+                .iter()
+                .find(|&&(_, offset, end)| (entry.start as u64) < end && offset < entry.end);
+            if let Some((name, _, _)) = overlapped {
+                return Err(NdsError::UnsupportedFormat(format!(
+                    "FAT entry {} overlaps the {}",
+                    i, name
+                )));
+            }
+            new_fat.0[i] = *entry;
+        } else {
+            pieces.push((entry.start, entry.end, Piece::File(i)));
+        }
+    }
+    pieces.sort_by_key(|&(start, end, _)| (start, end));
+
+    // Lay the rest out anew; entries sharing the same data keep sharing it
+    let mut new_hdr = NDSCartridgeHeader::parse_nds_bytes(hdr.as_bytes())?;
+    let mut moves: Vec<(FATEntry, FATEntry, Piece)> = vec![]; // (old, new, what) for each range
+    let mut pos = area_start;
+    for (start, end, piece) in pieces {
+        let entry = FATEntry { start, end };
+        if let Some(&(old, new, last)) = moves.last() {
+            match (last, piece) {
+                (Piece::File(_), Piece::File(i)) if (old.start, old.end) == (start, end) => {
+                    new_fat.0[i] = new;
+                    continue;
+                }
+                _ if start < old.end => {
+                    return Err(NdsError::UnsupportedFormat(format!(
+                        "{} at {:#x} overlaps the {} before it",
+                        piece.name(),
+                        start,
+                        last.name()
+                    )));
+                }
+                _ => {}
+            }
+        }
+        let new_start = pos.next_multiple_of(0x200);
+        pos = new_start + entry.len() as u64;
+        if pos > u32::MAX as u64 {
+            return Err(NdsError::InvalidInput("ROM would exceed 4GB".to_string()));
+        }
+        let new = FATEntry {
+            start: new_start as u32,
+            end: pos as u32,
+        };
+        match piece {
+            Piece::File(i) => new_fat.0[i] = new,
+            Piece::Fnt => new_hdr.fnt_offset = new.start,
+            Piece::Fat => new_hdr.fat_offset = new.start,
+        }
+        moves.push((entry, new, piece));
+    }
+
+    new_hdr.total_used_rom_size = pos as u32;
+    new_hdr.header_crc = new_hdr.compute_header_crc16();
+
+    if (new_hdr.fat_offset as u64) < area_start {
+        new_fat.write_to(&mut Cursor::new(&mut prefix[..]), &new_hdr)?;
+    }
+    prefix[..size_of::<NDSCartridgeHeader>()].copy_from_slice(new_hdr.as_bytes());
+    dst_rom.write_all(&prefix)?;
+
+    let mut read_pos = area_start;
+    let mut write_pos = area_start;
+    for (old, new, piece) in moves {
+        io::copy(
+            &mut src_rom.take(old.start as u64 - read_pos),
+            &mut io::sink(),
+        )?;
+        dst_rom.write_all(&vec![0xFF; (new.start as u64 - write_pos) as usize])?;
+        let copied = match piece {
+            Piece::Fat => {
+                for entry in &new_fat.0 {
+                    dst_rom.write_u32::<LittleEndian>(entry.start)?;
+                    dst_rom.write_u32::<LittleEndian>(entry.end)?;
+                }
+                io::copy(&mut src_rom.take(old.len() as u64), &mut io::sink())?
+            }
+            _ => io::copy(&mut src_rom.take(old.len() as u64), dst_rom)?,
+        };
+        if copied < old.len() as u64 {
+            return Err(NdsError::FileTooSmall {
+                expected: old.end as u64,
+                got: old.start as u64 + copied,
+            });
+        }
+        read_pos = old.end as u64;
+        write_pos = new.end as u64;
+    }
+
+    Ok(new_hdr)
+}
+
+// What compact_nitro_fs moves past the fixed sections.
+#[derive(Clone, Copy)]
+enum Piece {
+    File(usize),
+    Fnt,
+    Fat,
+}
+
+impl Piece {
+    fn name(&self) -> &'static str {
+        match self {
+            Piece::File(_) => "file",
+            Piece::Fnt => "FNT",
+            Piece::Fat => "FAT",
+        }
+    }
+}
+
+// Appends data at the next 200h boundary, padding with FFh, and returns where it starts.
+fn append_aligned(rom: &mut Vec<u8>, data: &[u8]) -> Result<u32> {
+    let offset = rom.len().next_multiple_of(0x200);
+    if offset + data.len() > u32::MAX as usize {
+        return Err(NdsError::InvalidInput("ROM would exceed 4GB".to_string()));
+    }
+    rom.resize(offset, 0xFF);
+    rom.extend_from_slice(data);
+    Ok(offset as u32)
+}
+
+// Reads the contents of a file given its FAT entry, failing if it runs past the end of the ROM.
+pub fn read_entry<R: Read + Seek, O: RomOffset>(
+    nds: &mut R,
+    entry: &FATEntry<O>,
+) -> Result<Vec<u8>> {
+    nds.seek(SeekFrom::Start(entry.start.into()))?;
+
+    let len: u64 = entry.len().into();
+    let mut contents: Vec<u8> = vec![0; len as usize];
+    nds.read_exact(&mut contents)?;
+
+    Ok(contents)
+}
+
+// The FNT and FAT together, mapping paths to file contents.
+pub struct NitroFS {
+    pub fnt: NitroFNT,
+    pub fat: NitroFAT,
+}
+
+impl NitroFS {
+    pub fn parse<R: Read + Seek>(nds: &mut R, hdr: &NDSCartridgeHeader) -> Result<NitroFS> {
+        Ok(NitroFS {
+            fnt: NitroFNT::parse(nds, hdr)?,
+            fat: NitroFAT::parse(nds, hdr)?,
+        })
+    }
+
+    /* Writes every file in the ROM's NitroFS under out_dir, recreating its directory tree.
+    Returns the number of files written. progress, if given, is called with the bytes written
+    so far and the total before each file, and once more at the end. */
+    pub fn extract_all<R: Read + Seek>(
+        nds: &mut R,
+        hdr: &NDSCartridgeHeader,
+        out_dir: &Path,
+        progress: Option<&dyn Fn(u64, u64)>,
+    ) -> Result<u32> {
+        let nitro = NitroFS::parse(nds, hdr)?;
+        let total: u64 = nitro.fat.0.iter().map(|e| e.len() as u64).sum();
+
+        let mut done = 0;
+        let written = nitro.extract_to(nds, out_dir, |_, size| {
+            if let Some(progress) = progress {
+                progress(done, total);
+            }
+            done += size as u64;
+        })?;
+        if let Some(progress) = progress {
+            progress(done, total);
+        }
+
+        Ok(written)
+    }
+
+    // As extract_all, calling on_file with each file's path (relative to out_dir) and size
+    // before it is written. File contents are streamed rather than read into memory.
+    pub fn extract_to<R: Read + Seek>(
+        &self,
+        nds: &mut R,
+        out_dir: &Path,
+        mut on_file: impl FnMut(&Path, u32),
+    ) -> Result<u32> {
+        let mut written = 0;
+
+        for (path, file_id) in self.fnt.iter() {
+            // Names come from the ROM, so don't let them escape out_dir
+            if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+                return Err(NdsError::UnsupportedFormat(format!(
+                    "unsafe NitroFS path {}",
+                    path.display()
+                )));
+            }
+
+            let entry = self.fat.0.get(file_id as usize).ok_or_else(|| {
+                NdsError::UnsupportedFormat(format!("file ID {} is not in the FAT", file_id))
+            })?;
+            on_file(&path, entry.len());
+
+            let out_path = out_dir.join(&path);
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            nds.seek(SeekFrom::Start(entry.start as u64))?;
+            let mut out = BufWriter::new(File::create(&out_path)?);
+            let copied = io::copy(&mut nds.take(entry.len() as u64), &mut out)?;
+            if copied != entry.len() as u64 {
+                return Err(NdsError::Io(io::ErrorKind::UnexpectedEof.into()));
+            }
+            out.flush()?;
+            written += 1;
+        }
+
+        Ok(written)
+    }
+
+    /* Writes a copy of src to dst with the given files (by ID) replaced, updating the FAT and
+    the header to match. A replacement that fits in its file's current slot is written in
+    place, with the rest of the slot filled with FFh. Anything larger is relocated to the end
+    of the image, aligned to 200h. Returns the number of files that had to be relocated.
+    progress, if given, is called with the replacement bytes written so far and their total
+    after each file. */
+    pub fn repack<R: Read + Seek, W: Read + Write + Seek>(
+        &mut self,
+        src: &mut R,
+        dst: &mut W,
+        hdr: &mut NDSCartridgeHeader,
+        replacements: &[(u16, Vec<u8>)],
+        progress: Option<&dyn Fn(u64, u64)>,
+    ) -> Result<u32> {
+        let total: u64 = replacements.iter().map(|(_, data)| data.len() as u64).sum();
+        let mut done = 0;
+
+        src.seek(SeekFrom::Start(0))?;
+        dst.seek(SeekFrom::Start(0))?;
+        let mut rom_end = io::copy(src, dst)?;
+        let mut relocated = 0;
+
+        for (file_id, data) in replacements {
+            let entry = self.fat.0.get_mut(*file_id as usize).ok_or_else(|| {
+                NdsError::InvalidInput(format!("file ID {} is not in the FAT", file_id))
+            })?;
+            let data_len = data.len() as u32;
+
+            if data_len <= entry.len() {
+                dst.seek(SeekFrom::Start(entry.start as u64))?;
+                dst.write_all(data)?;
+                dst.write_all(&vec![0xFF; (entry.len() - data_len) as usize])?;
+            } else {
+                let start = (rom_end + 0x1FF) & !0x1FF;
+                if start + data.len() as u64 > u32::MAX as u64 {
+                    return Err(NdsError::InvalidInput(
+                        "repacked ROM would exceed 4GB".to_string(),
+                    ));
+                }
+                dst.seek(SeekFrom::Start(rom_end))?;
+                dst.write_all(&vec![0xFF; (start - rom_end) as usize])?;
+                dst.write_all(data)?;
+
+                entry.start = start as u32;
+                rom_end = start + data.len() as u64;
+                relocated += 1;
+            }
+            entry.end = entry.start + data_len;
+
+            done += data.len() as u64;
+            if let Some(progress) = progress {
+                progress(done, total);
+            }
+        }
+
+        self.fat.write_to(dst, hdr)?;
+
+        let used_end = self.fat.0.iter().map(|e| e.end).max().unwrap_or(0);
+        if used_end > hdr.total_used_rom_size {
+            hdr.total_used_rom_size = used_end;
+        }
+        hdr.write_to(dst)?;
+
+        Ok(relocated)
+    }
+}
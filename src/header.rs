@@ -0,0 +1,761 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::mem::{size_of, transmute};
+
+use crate::crc::bios_get_crc16;
+use crate::crypto::key2_seed;
+use crate::error::NdsError;
+
+const AUTOSTART_SKIP_MENU: u8 = 0x04;
+
+// The console a title is built for, from the header's unit code (012h).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitCode {
+    Nds,
+    NdsDsi,
+    DsiOnly,
+    Unknown(u8),
+}
+
+impl From<u8> for UnitCode {
+    fn from(code: u8) -> Self {
+        match code {
+            0x00 => UnitCode::Nds,
+            0x02 => UnitCode::NdsDsi,
+            0x03 => UnitCode::DsiOnly,
+            other => UnitCode::Unknown(other),
+        }
+    }
+}
+
+impl fmt::Display for UnitCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnitCode::Nds => write!(f, "NDS"),
+            UnitCode::NdsDsi => write!(f, "NDS+DSi"),
+            UnitCode::DsiOnly => write!(f, "DSi only"),
+            UnitCode::Unknown(code) => write!(f, "Unknown ({:#04x})", code),
+        }
+    }
+}
+
+/* The sales region. The header's region byte (01Dh) only singles out the China (80h) and
+Korea (40h) consoles, everything else being 00h, so From<u8> gives World for 00h and region()
+narrows it down using the region letter of the gamecode. */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NdsRegion {
+    Japan,
+    Usa,
+    Europe,
+    Australia,
+    China,
+    Korea,
+    World,
+    Unknown(u8),
+}
+
+impl From<u8> for NdsRegion {
+    fn from(code: u8) -> Self {
+        match code {
+            0x00 => NdsRegion::World,
+            0x40 => NdsRegion::Korea,
+            0x80 => NdsRegion::China,
+            other => NdsRegion::Unknown(other),
+        }
+    }
+}
+
+impl fmt::Display for NdsRegion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NdsRegion::Japan => write!(f, "Japan"),
+            NdsRegion::Usa => write!(f, "USA"),
+            NdsRegion::Europe => write!(f, "Europe"),
+            NdsRegion::Australia => write!(f, "Australia"),
+            NdsRegion::China => write!(f, "China"),
+            NdsRegion::Korea => write!(f, "Korea"),
+            NdsRegion::World => write!(f, "World"),
+            NdsRegion::Unknown(code) => write!(f, "Unknown ({:#04x})", code),
+        }
+    }
+}
+
+// The CRC16 of the Nintendo logo at [0C0h..15Bh], which the BIOS refuses to boot without.
+pub const LOGO_CRC16: u16 = 0xCF56;
+
+#[derive(Debug, PartialEq, Eq)]
+#[repr(C, packed(1))]
+pub struct NDSCartridgeHeader {
+    pub gametitle: [u8; 12],
+    pub gamecode: u32,
+    pub makercode: u16,
+    /* 012h..01Fh are all single bytes, whatever some descriptions of a 2-byte "secure area key"
+    field suggest: nothing about the secure area is stored here, the KEY1 seed being the
+    gamecode and any disabling being done at 078h. */
+    pub unitcode: [u8; 1],    // 012h
+    pub encrseedsel: [u8; 1], // 013h, selects the KEY2 seed
+    pub devicecaps: [u8; 1],  // 014h, chip capacity as 128KB << n
+    pub res0: [u8; 8],        // 015h..01Ch, reserved (01Ch holds flags on DSi titles)
+    pub ndsregion: [u8; 1],   // 01Dh
+    pub romversion: [u8; 1],  // 01Eh
+    pub autostart: [u8; 1],   // 01Fh
+    pub arm9off: u32,
+    pub arm9entry: u32,
+    pub arm9raddr: u32,
+    pub arm9size: u32,
+    pub arm7off: u32,
+    pub arm7entry: u32,
+    pub arm7raddr: u32,
+    pub arm7size: u32,
+    pub fnt_offset: u32,
+    pub fnt_size: u32,
+    pub fat_offset: u32,
+    pub fat_size: u32,
+    pub arm9_overlay_offset: u32,
+    pub arm9_overlay_size: u32,
+    pub arm7_overlay_offset: u32,
+    pub arm7_overlay_size: u32,
+    pub romctrl_normal: u32, // Port 40001A4h setting for normal commands
+    pub romctrl_key1: u32,   // Port 40001A4h setting for KEY1 commands
+    pub banner_offset: u32,
+    pub secure_area_crc: u16, // CRC16 of [arm9off..8000h]
+    pub secure_area_delay: u16,
+    pub arm9_autoload_hook: u32,
+    pub arm7_autoload_hook: u32,
+    pub secure_area_disable: [u8; 8], // "NmMdOnly", encrypted, when the secure area is disabled
+    pub total_used_rom_size: u32,
+    pub header_size: u32,
+    pub res1: [u8; 0x38],
+    pub logo: [u8; 0x9C],
+    pub logo_crc: u16,   // CRC16 of [0C0h..15Bh], always CF56h
+    pub header_crc: u16, // CRC16 of [000h..15Dh]
+    pub debug_rom_offset: u32,
+    pub debug_size: u32,
+    pub debug_ram_address: u32,
+    pub res2: [u8; 0x14],
+}
+
+// The header runs from 000h to the end of res2 at 17Fh; the DSi extension starts at 180h
+const _: () = assert!(size_of::<NDSCartridgeHeader>() == 0x180);
+
+// Arrays longer than 32 elements don't implement Default, so zero-fill by hand.
+impl Default for NDSCartridgeHeader {
+    fn default() -> Self {
+        unsafe { std::mem::zeroed() }
+    }
+}
+
+// The DSi (TWL) extension of the header at [180h..400h], present on DSi-enhanced and
+// DSi-exclusive titles.
+#[repr(C, packed(1))]
+pub struct DSiExtendedHeader {
+    pub mbk1_5: [u32; 5],      // Global MBK1..MBK5 settings
+    pub mbk6_8_arm9: [u32; 3], // Local MBK6..MBK8 settings for the ARM9
+    pub mbk6_8_arm7: [u32; 3], // Local MBK6..MBK8 settings for the ARM7
+    pub mbk9: [u8; 3],
+    pub wramcnt: u8,
+    pub region_flags: u32,
+    pub access_control: u32,
+    pub arm7_scfg_mask: u32,
+    pub res0: [u8; 3],
+    pub twl_flags: u8,
+    pub arm9i_off: u32,
+    pub res1: u32,
+    pub arm9i_raddr: u32,
+    pub arm9i_size: u32,
+    pub arm7i_off: u32,
+    pub sd_device_list_addr: u32,
+    pub arm7i_raddr: u32,
+    pub arm7i_size: u32,
+    pub digest_ntr_offset: u32,
+    pub digest_ntr_size: u32,
+    pub digest_twl_offset: u32,
+    pub digest_twl_size: u32,
+    pub digest_sector_hashtable_offset: u32,
+    pub digest_sector_hashtable_size: u32,
+    pub digest_block_hashtable_offset: u32,
+    pub digest_block_hashtable_size: u32,
+    pub digest_sector_size: u32,
+    pub digest_block_sectorcount: u32,
+    pub banner_size: u32,
+    pub sd_size1: u32,
+    pub total_used_rom_size_twl: u32,
+    pub sd_size2: u32,
+    pub unknown0: u32,
+    pub unknown1: u32,
+    pub modcrypt1_offset: u32,
+    pub modcrypt1_size: u32,
+    pub modcrypt2_offset: u32,
+    pub modcrypt2_size: u32,
+    pub title_id_gamecode: [u8; 4], // The gamecode again, spelled backwards
+    pub title_id_hi: u32,           // Title type, e.g. 00030004h for DSiWare
+    pub public_sav_size: u32,
+    pub private_sav_size: u32,
+    pub res2: [u8; 0xB0],
+    pub age_ratings: [u8; 0x10],
+    pub hmac_arm9: [u8; 20], // SHA1-HMAC of the ARM9 including the encrypted secure area
+    pub hmac_arm7: [u8; 20],
+    pub hmac_digest_master: [u8; 20],
+    pub hmac_banner: [u8; 20],
+    pub hmac_arm9i: [u8; 20], // Of the decrypted ARM9i
+    pub hmac_arm7i: [u8; 20], // Of the decrypted ARM7i
+    pub res3: [u8; 40],
+    pub hmac_arm9_no_secure_area: [u8; 20],
+    pub res4: [u8; 0x4C],
+}
+
+// 180h..3FFh, so that the fields past the padding line up with GBATEK's offsets
+const _: () = assert!(size_of::<DSiExtendedHeader>() == 0x280);
+
+impl Default for DSiExtendedHeader {
+    fn default() -> Self {
+        unsafe { std::mem::zeroed() }
+    }
+}
+
+/* The header from the start of a ROM already in memory, as parse_nds reads it. Anything past the
+header is ignored; a slice shorter than the header fails with BufferTooSmall. */
+impl TryFrom<&[u8]> for NDSCartridgeHeader {
+    type Error = NdsError;
+
+    fn try_from(data: &[u8]) -> Result<Self, NdsError> {
+        let expected = size_of::<NDSCartridgeHeader>();
+        match data.get(..expected) {
+            Some(raw) => Ok(Self::from_raw(raw.try_into().unwrap())),
+            None => Err(NdsError::BufferTooSmall {
+                expected,
+                got: data.len(),
+            }),
+        }
+    }
+}
+
+impl NDSCartridgeHeader {
+    /* Reads the header from the start of a ROM. Fails with FileTooSmall if the ROM is shorter
+    than the header, rather than handing back a partly-read one. */
+    pub fn parse_nds<R: Read + Seek>(mut cart: R) -> Result<Self, NdsError> {
+        let len = cart.seek(SeekFrom::End(0))?;
+        let expected = size_of::<NDSCartridgeHeader>() as u64;
+        if len < expected {
+            return Err(NdsError::FileTooSmall { expected, got: len });
+        }
+
+        let mut raw = [0u8; size_of::<NDSCartridgeHeader>()];
+        cart.seek(SeekFrom::Start(0))?;
+        cart.read_exact(&mut raw)?;
+
+        Ok(Self::from_raw(&raw))
+    }
+
+    fn from_raw(raw: &[u8; size_of::<NDSCartridgeHeader>()]) -> Self {
+        let mut hdr = Self::default();
+        let hdrptr = unsafe {
+            transmute::<&mut NDSCartridgeHeader, &mut [u8; size_of::<NDSCartridgeHeader>()]>(
+                &mut hdr,
+            )
+        };
+        hdrptr.copy_from_slice(raw);
+        hdr
+    }
+
+    // parse_nds over an in-memory ROM image.
+    pub fn parse_nds_bytes(data: &[u8]) -> Result<Self, NdsError> {
+        Self::parse_nds(Cursor::new(data))
+    }
+
+    /* Reads the DSi extended header that follows this header, if the unit code says there is
+    one: 02h (NDS+DSi) or 03h (DSi only). Returns None for plain NDS titles, or if the ROM is
+    too short to hold the extension. */
+    pub fn parse_dsi_extension<R: Read + Seek>(&self, r: &mut R) -> Option<DSiExtendedHeader> {
+        if !self.is_dsi_compatible() {
+            return None;
+        }
+
+        let mut ext = DSiExtendedHeader::default();
+        let extptr = unsafe {
+            transmute::<&mut DSiExtendedHeader, &mut [u8; size_of::<DSiExtendedHeader>()]>(&mut ext)
+        };
+
+        r.seek(SeekFrom::Start(size_of::<NDSCartridgeHeader>() as u64))
+            .ok()?;
+        r.read_exact(extptr).ok()?;
+
+        Some(ext)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; size_of::<NDSCartridgeHeader>()] {
+        unsafe { transmute::<&NDSCartridgeHeader, &[u8; size_of::<NDSCartridgeHeader>()]>(self) }
+    }
+
+    /* Where the loaded ARM9 bootcode starts executing: the firmware copies it to the RAM
+    address, then branches to the entry address. Any decompression of the binary is done by
+    the code itself, after that branch. */
+    pub fn arm9_entry_address(&self) -> u32 {
+        self.arm9entry
+    }
+
+    pub fn arm9_ram_address(&self) -> u32 {
+        self.arm9raddr
+    }
+
+    // As for the ARM9, but for the ARM7 bootcode.
+    pub fn arm7_entry_address(&self) -> u32 {
+        self.arm7entry
+    }
+
+    pub fn arm7_ram_address(&self) -> u32 {
+        self.arm7raddr
+    }
+
+    // The cartridge chip size declared at 014h, 128KB << n. Out-of-range values saturate.
+    pub fn rom_capacity_bytes(&self) -> u64 {
+        let shift = self.devicecaps[0] as u32;
+        if shift > 46 {
+            return u64::MAX;
+        }
+        0x20000u64 << shift
+    }
+
+    /* The encryption seed select at 013h, 00h..07h (usually 00h). It picks the KEY2 seed
+    byte, see crypto::key2_seed; KEY1 (and with it the secure area) doesn't depend on it. */
+    pub fn encryption_seed_select(&self) -> u8 {
+        self.encrseedsel[0]
+    }
+
+    /* Whether the secure area has been disabled, as on some development cartridges. Such ROMs
+    hold "NmMdOnly" (KEY1-encrypted) at 078h, and their ARM9 isn't encrypted at all; in
+    retail ROMs the field is zero. */
+    pub fn secure_area_disable(&self) -> bool {
+        self.secure_area_disable != [0; 8]
+    }
+
+    pub fn unit_code(&self) -> UnitCode {
+        UnitCode::from(self.unitcode[0])
+    }
+
+    // Whether the unit code is 02h (NDS+DSi) or 03h (DSi only), with the DSi header extension.
+    pub fn is_dsi_compatible(&self) -> bool {
+        matches!(self.unit_code(), UnitCode::NdsDsi | UnitCode::DsiOnly)
+    }
+
+    // The opposite of is_dsi_compatible; unknown unit codes count as plain NDS.
+    pub fn is_nds_only(&self) -> bool {
+        !self.is_dsi_compatible()
+    }
+
+    /* Bit 2 of the autostart flags (01Fh) makes the firmware skip the "Press Button" prompt
+    after the Health and Safety screen, and the boot menu along with it. The other bits have
+    no known use and are normally zero. */
+    pub fn autostart_enabled(&self) -> bool {
+        self.autostart[0] & AUTOSTART_SKIP_MENU != 0
+    }
+
+    pub fn set_autostart(&mut self, enabled: bool) {
+        if enabled {
+            self.autostart[0] |= AUTOSTART_SKIP_MENU;
+        } else {
+            self.autostart[0] &= !AUTOSTART_SKIP_MENU;
+        }
+    }
+
+    pub fn region(&self) -> NdsRegion {
+        match NdsRegion::from(self.ndsregion[0]) {
+            NdsRegion::World => match self.game_code_str()[3] {
+                b'J' => NdsRegion::Japan,
+                b'E' => NdsRegion::Usa,
+                b'P' => NdsRegion::Europe,
+                b'U' => NdsRegion::Australia,
+                b'C' => NdsRegion::China,
+                b'K' => NdsRegion::Korea,
+                _ => NdsRegion::World,
+            },
+            region => region,
+        }
+    }
+
+    /* Retargets the header at another region, undoing region(): the region byte at 01Dh becomes
+    80h for China, 40h for Korea and 00h otherwise, and the last gamecode letter becomes the
+    region's (J, E, P, U, C or K). The maker code names the publisher, not the region, and is
+    left alone. As the gamecode is also the KEY1 seed, an encrypted secure area has to be
+    re-encrypted to match; the CRC16s are brought up to date by write_to. World and Unknown
+    aren't regions a ROM can be targeted at, and are refused. */
+    pub fn set_region(&mut self, region: NdsRegion) -> Result<(), NdsError> {
+        let (region_byte, letter) = match region {
+            NdsRegion::Japan => (0x00, b'J'),
+            NdsRegion::Usa => (0x00, b'E'),
+            NdsRegion::Europe => (0x00, b'P'),
+            NdsRegion::Australia => (0x00, b'U'),
+            NdsRegion::China => (0x80, b'C'),
+            NdsRegion::Korea => (0x40, b'K'),
+            NdsRegion::World | NdsRegion::Unknown(_) => {
+                return Err(NdsError::InvalidInput(format!(
+                    "cannot target a ROM at region {}",
+                    region
+                )))
+            }
+        };
+
+        let mut gamecode = self.game_code_str();
+        gamecode[3] = letter;
+        self.gamecode = u32::from_le_bytes(gamecode);
+        self.ndsregion = [region_byte];
+        Ok(())
+    }
+
+    // The game title, without its NUL padding. Empty if it isn't valid ASCII/UTF-8.
+    pub fn game_title(&self) -> &str {
+        let len = self
+            .gametitle
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(self.gametitle.len());
+        std::str::from_utf8(&self.gametitle[..len]).unwrap_or("")
+    }
+
+    // The gamecode bytes in the order they appear in the ROM (e.g. b"IPKE"), rather than as
+    // little-endian integer digits.
+    pub fn game_code_str(&self) -> [u8; 4] {
+        let gamecode = self.gamecode;
+        gamecode.to_le_bytes()
+    }
+
+    // The gamecode as printable text, with any non-printable byte shown as '.'.
+    pub fn game_code_display(&self) -> String {
+        self.game_code_str()
+            .iter()
+            .map(|&b| if b.is_ascii_graphic() { b as char } else { '.' })
+            .collect()
+    }
+
+    /* The two-character maker code, or "??" if either byte isn't printable ASCII (as in the
+    zero-filled headers of most homebrew). Some of the licensees behind them: 01 Nintendo,
+    08 Capcom, 13 and 69 Electronic Arts, 41 Ubisoft, 52 Activision, 78 THQ, 8P Sega, A4 Konami,
+    AF Namco, B2 Bandai, GD Square Enix. */
+    pub fn maker_code_str(&self) -> Cow<'_, str> {
+        // makercode is unaligned, so go through the raw bytes rather than referencing it
+        let code = &self.as_bytes()[0x10..0x12];
+        if code.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+            Cow::Borrowed(std::str::from_utf8(code).unwrap())
+        } else {
+            Cow::Borrowed("??")
+        }
+    }
+
+    // CRC16 of [000h..15Dh], i.e. everything up to the header CRC16 field itself.
+    pub fn compute_header_crc16(&self) -> u16 {
+        bios_get_crc16(&self.as_bytes()[..0x15E])
+    }
+
+    pub fn validate_header_crc16(&self) -> bool {
+        self.compute_header_crc16() == self.header_crc
+    }
+
+    /* Whether the Nintendo logo is intact: it must hash to CF56h, and the stored logo CRC16 must
+    say so too. A zero-filled header fails, as the CRC of an empty logo is something else. */
+    pub fn is_valid_magic(&self) -> bool {
+        self.logo_crc == LOGO_CRC16 && bios_get_crc16(&self.logo) == LOGO_CRC16
+    }
+
+    /* Writes the header at offset 0, first bringing both of its CRC16s up to date.
+    The secure area CRC16 (06Ch) covers [arm9off..8000h] of the ROM as it is stored, so it is
+    read back from `w`: the ARM9 bootcode must already have been written by this point. */
+    pub fn write_to<W: Read + Write + Seek>(&mut self, w: &mut W) -> Result<(), NdsError> {
+        let arm9off = self.arm9off;
+        if (0x4000..0x8000).contains(&arm9off) {
+            let mut secure_area = vec![0u8; (0x8000 - arm9off) as usize];
+            w.seek(SeekFrom::Start(arm9off as u64))?;
+            w.read_exact(&mut secure_area)?;
+            self.secure_area_crc = bios_get_crc16(&secure_area);
+        }
+
+        self.header_crc = self.compute_header_crc16();
+
+        w.seek(SeekFrom::Start(0))?;
+        w.write_all(self.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+/* One row per field, as in GBATEK's description of the header: offset, field name, the raw value
+in hex (bytes in ROM order for the byte arrays) and what it means, where there is more to say
+than the number itself. The reserved areas and the logo bitmap are left out. */
+impl NDSCartridgeHeader {
+    // The field table behind Display, `info` and `diff`; the meaning is empty where there is none.
+    pub fn rows(&self) -> Vec<(u32, &'static str, String, String)> {
+        let bytes = |b: &[u8]| {
+            b.iter()
+                .map(|b| format!("{:02X}", b))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let byte = |b: u8| format!("{:02X}", b);
+        let half = |h: u16| format!("{:04X}", h);
+        let word = |w: u32| format!("{:08X}", w);
+        let size = |n: u32| format!("{} bytes", n);
+
+        let seed = match key2_seed(self.encryption_seed_select()) {
+            Ok(seed) => format!("KEY2 seed {:02X}h", seed),
+            Err(_) => "invalid".to_string(),
+        };
+        vec![
+            (
+                0x000,
+                "Game title",
+                bytes(&self.gametitle),
+                self.game_title().to_string(),
+            ),
+            (
+                0x00C,
+                "Game code",
+                word(self.gamecode),
+                self.game_code_display(),
+            ),
+            (
+                0x010,
+                "Maker code",
+                half(self.makercode),
+                self.maker_code_str().to_string(),
+            ),
+            (
+                0x012,
+                "Unit code",
+                byte(self.unitcode[0]),
+                self.unit_code().to_string(),
+            ),
+            (
+                0x013,
+                "Encryption seed select",
+                byte(self.encrseedsel[0]),
+                seed,
+            ),
+            (
+                0x014,
+                "Device capacity",
+                byte(self.devicecaps[0]),
+                format!("{} KB", self.rom_capacity_bytes() / 1024),
+            ),
+            (
+                0x01D,
+                "NDS region",
+                byte(self.ndsregion[0]),
+                self.region().to_string(),
+            ),
+            (
+                0x01E,
+                "ROM version",
+                byte(self.romversion[0]),
+                String::new(),
+            ),
+            (
+                0x01F,
+                "Autostart",
+                byte(self.autostart[0]),
+                if self.autostart_enabled() {
+                    "skips menu".to_string()
+                } else {
+                    String::new()
+                },
+            ),
+            (0x020, "ARM9 ROM offset", word(self.arm9off), String::new()),
+            (
+                0x024,
+                "ARM9 entry address",
+                word(self.arm9entry),
+                String::new(),
+            ),
+            (
+                0x028,
+                "ARM9 RAM address",
+                word(self.arm9raddr),
+                String::new(),
+            ),
+            (0x02C, "ARM9 size", word(self.arm9size), size(self.arm9size)),
+            (0x030, "ARM7 ROM offset", word(self.arm7off), String::new()),
+            (
+                0x034,
+                "ARM7 entry address",
+                word(self.arm7entry),
+                String::new(),
+            ),
+            (
+                0x038,
+                "ARM7 RAM address",
+                word(self.arm7raddr),
+                String::new(),
+            ),
+            (0x03C, "ARM7 size", word(self.arm7size), size(self.arm7size)),
+            (0x040, "FNT offset", word(self.fnt_offset), String::new()),
+            (0x044, "FNT size", word(self.fnt_size), size(self.fnt_size)),
+            (0x048, "FAT offset", word(self.fat_offset), String::new()),
+            (
+                0x04C,
+                "FAT size",
+                word(self.fat_size),
+                format!("{} files", self.fat_size / 8),
+            ),
+            (
+                0x050,
+                "ARM9 overlay offset",
+                word(self.arm9_overlay_offset),
+                String::new(),
+            ),
+            (
+                0x054,
+                "ARM9 overlay size",
+                word(self.arm9_overlay_size),
+                format!("{} overlays", self.arm9_overlay_size / 32),
+            ),
+            (
+                0x058,
+                "ARM7 overlay offset",
+                word(self.arm7_overlay_offset),
+                String::new(),
+            ),
+            (
+                0x05C,
+                "ARM7 overlay size",
+                word(self.arm7_overlay_size),
+                format!("{} overlays", self.arm7_overlay_size / 32),
+            ),
+            (
+                0x060,
+                "ROM control (normal)",
+                word(self.romctrl_normal),
+                String::new(),
+            ),
+            (
+                0x064,
+                "ROM control (KEY1)",
+                word(self.romctrl_key1),
+                String::new(),
+            ),
+            (
+                0x068,
+                "Banner offset",
+                word(self.banner_offset),
+                match self.banner_offset {
+                    0 => "no banner".to_string(),
+                    _ => String::new(),
+                },
+            ),
+            (
+                0x06C,
+                "Secure area CRC16",
+                half(self.secure_area_crc),
+                String::new(),
+            ),
+            (
+                0x06E,
+                "Secure area delay",
+                half(self.secure_area_delay),
+                String::new(),
+            ),
+            (
+                0x070,
+                "ARM9 autoload hook",
+                word(self.arm9_autoload_hook),
+                String::new(),
+            ),
+            (
+                0x074,
+                "ARM7 autoload hook",
+                word(self.arm7_autoload_hook),
+                String::new(),
+            ),
+            (
+                0x078,
+                "Secure area disable",
+                bytes(&self.secure_area_disable),
+                if self.secure_area_disable() {
+                    "disabled".to_string()
+                } else {
+                    String::new()
+                },
+            ),
+            (
+                0x080,
+                "Total used ROM size",
+                word(self.total_used_rom_size),
+                size(self.total_used_rom_size),
+            ),
+            (
+                0x084,
+                "Header size",
+                word(self.header_size),
+                size(self.header_size),
+            ),
+            (
+                0x15C,
+                "Logo CRC16",
+                half(self.logo_crc),
+                if self.is_valid_magic() {
+                    "valid logo"
+                } else {
+                    "invalid logo"
+                }
+                .to_string(),
+            ),
+            (
+                0x15E,
+                "Header CRC16",
+                half(self.header_crc),
+                if self.validate_header_crc16() {
+                    "OK".to_string()
+                } else {
+                    format!("BAD, should be {:04X}", self.compute_header_crc16())
+                },
+            ),
+            (
+                0x160,
+                "Debug ROM offset",
+                word(self.debug_rom_offset),
+                String::new(),
+            ),
+            (
+                0x164,
+                "Debug size",
+                word(self.debug_size),
+                size(self.debug_size),
+            ),
+            (
+                0x168,
+                "Debug RAM address",
+                word(self.debug_ram_address),
+                String::new(),
+            ),
+        ]
+    }
+}
+
+impl fmt::Display for NDSCartridgeHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (offset, name, raw, meaning) in self.rows() {
+            let row = format!("{:03X}h  {:<24} {:<35} {}", offset, name, raw, meaning);
+            writeln!(f, "{}", row.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/* The header as a hex dump in the style of `xxd`: 16 bytes per line, as offset, eight groups
+of two bytes, and the bytes as ASCII with anything unprintable shown as '.'. */
+pub fn dump_header_hex(hdr: &NDSCartridgeHeader) -> String {
+    let mut out = String::new();
+    for (i, line) in hdr.as_bytes().chunks(16).enumerate() {
+        let hex: Vec<String> = line
+            .chunks(2)
+            .map(|pair| pair.iter().map(|b| format!("{:02x}", b)).collect())
+            .collect();
+        let ascii: String = line
+            .iter()
+            .map(|&b| match b {
+                0x20..=0x7E => b as char,
+                _ => '.',
+            })
+            .collect();
+        out.push_str(&format!("{:08x}: {}  {}\n", i * 16, hex.join(" "), ascii));
+    }
+    out
+}
@@ -0,0 +1,35 @@
+// Without the std feature only the crc and crypto modules are built, and the crate is no_std.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+pub mod banner;
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod blz;
+#[cfg(feature = "std")]
+pub mod bootcode;
+#[cfg(feature = "std")]
+pub mod builder;
+pub mod crc;
+pub mod crypto;
+#[cfg(feature = "std")]
+pub mod digest;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
+pub mod filesystem;
+#[cfg(feature = "std")]
+pub mod header;
+#[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+pub mod mmap;
+#[cfg(feature = "std")]
+pub mod overlay;
+#[cfg(feature = "std")]
+mod png;
+#[cfg(feature = "std")]
+pub mod rom;
+#[cfg(feature = "rom-db")]
+pub mod romdb;
+#[cfg(feature = "std")]
+pub mod validate;
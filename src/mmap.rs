@@ -0,0 +1,127 @@
+// ------------------------------------------------------------
+// Reading a ROM through a read-only memory mapping
+// ------------------------------------------------------------
+
+/* For DSi-era ROMs of hundreds of megabytes, mapping the file lets the OS page in only the parts
+that are actually read. MmapNdsRom is Read + Seek like a File, so it can be handed to any of the
+parsers, and derefs to the whole image as a byte slice. The mapping is made with mmap(2)
+directly, so this is only built for 64-bit unix targets. */
+
+use std::ffi::c_void;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Deref;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::ptr;
+
+const PROT_READ: i32 = 1;
+const MAP_PRIVATE: i32 = 2;
+const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+extern "C" {
+    fn mmap(addr: *mut c_void, len: usize, prot: i32, flags: i32, fd: i32, off: i64)
+        -> *mut c_void;
+    fn munmap(addr: *mut c_void, len: usize) -> i32;
+}
+
+pub struct MmapNdsRom {
+    ptr: *const u8, // Null for an empty file, which can't be mapped
+    len: usize,
+    pos: u64,
+}
+
+// The mapping is read-only and owned by this value alone
+unsafe impl Send for MmapNdsRom {}
+unsafe impl Sync for MmapNdsRom {}
+
+impl MmapNdsRom {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::map(&File::open(path)?)
+    }
+
+    /* Maps the whole of an open file. The file can be closed afterwards; the mapping stays valid
+    until this is dropped. As with any mapping, the file shouldn't be truncated meanwhile. */
+    pub fn map(file: &File) -> io::Result<Self> {
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file too large to map"))?;
+        if len == 0 {
+            return Ok(MmapNdsRom {
+                ptr: ptr::null(),
+                len: 0,
+                pos: 0,
+            });
+        }
+
+        let ptr = unsafe {
+            mmap(
+                ptr::null_mut(),
+                len,
+                PROT_READ,
+                MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(MmapNdsRom {
+            ptr: ptr as *const u8,
+            len,
+            pos: 0,
+        })
+    }
+}
+
+impl Deref for MmapNdsRom {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        if self.ptr.is_null() {
+            return &[];
+        }
+        // ptr is a live read-only mapping of len bytes
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for MmapNdsRom {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe { munmap(self.ptr as *mut c_void, self.len) };
+        }
+    }
+}
+
+impl Read for MmapNdsRom {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = self.pos.min(self.len as u64) as usize;
+        let n = buf.len().min(self.len - start);
+        buf[..n].copy_from_slice(&self[start..start + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+// As with a File, seeking past the end is allowed and reads there return nothing.
+impl Seek for MmapNdsRom {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => (self.len as u64).checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        match new_pos {
+            Some(new_pos) => {
+                self.pos = new_pos;
+                Ok(new_pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
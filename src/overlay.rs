@@ -0,0 +1,99 @@
+use std::fmt::Write as _;
+use std::io::{Read, Seek, SeekFrom};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use crate::error::{NdsError, Result};
+use crate::filesystem::NitroFAT;
+use crate::header::NDSCartridgeHeader;
+
+// One 32-byte entry of an overlay table (OVT).
+#[derive(Clone, Copy)]
+pub struct OverlayEntry {
+    pub overlay_id: u32,
+    pub ram_address: u32,
+    pub ram_size: u32,
+    pub bss_size: u32,
+    pub static_init_start: u32,
+    pub static_init_end: u32,
+    pub file_id: u32,  // Index into the FAT
+    pub reserved: u32, // Compressed size and flags, in practice
+}
+
+pub struct OverlayTable(pub Vec<OverlayEntry>);
+
+impl OverlayTable {
+    pub fn parse_arm9<R: Read + Seek>(nds: &mut R, hdr: &NDSCartridgeHeader) -> Result<Self> {
+        Self::parse(nds, hdr.arm9_overlay_offset, hdr.arm9_overlay_size)
+    }
+
+    pub fn parse_arm7<R: Read + Seek>(nds: &mut R, hdr: &NDSCartridgeHeader) -> Result<Self> {
+        Self::parse(nds, hdr.arm7_overlay_offset, hdr.arm7_overlay_size)
+    }
+
+    fn parse<R: Read + Seek>(nds: &mut R, offset: u32, size: u32) -> Result<Self> {
+        let mut entries: Vec<OverlayEntry> = Vec::with_capacity(size as usize / 32);
+        if size == 0 {
+            return Ok(OverlayTable(entries));
+        }
+
+        nds.seek(SeekFrom::Start(offset as u64))?;
+        for _ in 0..size / 32 {
+            let mut fields = [0u32; 8];
+            nds.read_u32_into::<LittleEndian>(&mut fields)?;
+            entries.push(OverlayEntry {
+                overlay_id: fields[0],
+                ram_address: fields[1],
+                ram_size: fields[2],
+                bss_size: fields[3],
+                static_init_start: fields[4],
+                static_init_end: fields[5],
+                file_id: fields[6],
+                reserved: fields[7],
+            });
+        }
+
+        Ok(OverlayTable(entries))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, OverlayEntry> {
+        self.0.iter()
+    }
+}
+
+// The overlay's file, exactly as stored in the ROM (still compressed, if it is).
+pub fn extract_overlay<R: Read + Seek>(
+    r: &mut R,
+    fat: &NitroFAT,
+    entry: &OverlayEntry,
+) -> Result<Vec<u8>> {
+    let file_id = u16::try_from(entry.file_id).map_err(|_| {
+        NdsError::InvalidInput(format!(
+            "overlay {} has file ID {:#x}, beyond the FAT",
+            entry.overlay_id, entry.file_id
+        ))
+    })?;
+    fat.read_file_by_id(file_id, r)
+}
+
+// A table of both overlay tables' entries: CPU, ID, RAM address, RAM size and file ID.
+pub fn overlay_summary(arm9_overlays: &OverlayTable, arm7_overlays: &OverlayTable) -> String {
+    let mut out = String::new();
+    writeln!(out, "CPU   ID    RAM address  Size      File ID").unwrap();
+    for (cpu, table) in [("ARM9", arm9_overlays), ("ARM7", arm7_overlays)] {
+        for entry in table.iter() {
+            writeln!(
+                out,
+                "{}  {:<4}  {:#010x}   {:#08x}  {}",
+                cpu, entry.overlay_id, entry.ram_address, entry.ram_size, entry.file_id
+            )
+            .unwrap();
+        }
+    }
+    out
+}
+
+// Prints overlay_summary to stdout.
+pub fn list_overlays(arm9_overlays: &OverlayTable, arm7_overlays: &OverlayTable) {
+    print!("{}", overlay_summary(arm9_overlays, arm7_overlays));
+}
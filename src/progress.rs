@@ -0,0 +1,51 @@
+// A one-line progress bar on stderr for the commands that copy a lot of data. Nothing is drawn
+// unless stderr is a terminal, so redirected output stays clean.
+
+use std::cell::Cell;
+use std::io::{self, IsTerminal, Write};
+
+const BAR_WIDTH: u64 = 30;
+
+pub struct ProgressBar {
+    label: &'static str,
+    enabled: bool,
+    drawn: Cell<Option<u64>>, // The percentage last drawn
+}
+
+impl ProgressBar {
+    pub fn new(label: &'static str) -> Self {
+        ProgressBar {
+            label,
+            enabled: io::stderr().is_terminal(),
+            drawn: Cell::new(None),
+        }
+    }
+
+    // Redraws the bar for `done` bytes out of `total`, if the percentage has moved.
+    pub fn update(&self, done: u64, total: u64) {
+        let percent = (done * 100).checked_div(total).unwrap_or(100);
+        if !self.enabled || self.drawn.get() == Some(percent) {
+            return;
+        }
+        self.drawn.set(Some(percent));
+
+        let filled = (percent * BAR_WIDTH / 100) as usize;
+        let _ = write!(
+            io::stderr(),
+            "\r{} [{}{}] {:3}% ({} / {} KiB)",
+            self.label,
+            "#".repeat(filled),
+            " ".repeat(BAR_WIDTH as usize - filled),
+            percent,
+            done / 1024,
+            total / 1024
+        );
+    }
+
+    // Ends the bar's line, if one was drawn.
+    pub fn finish(&self) {
+        if self.drawn.get().is_some() {
+            eprintln!();
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use crate::banner::{banner_size, NDSBanner};
+use crate::bootcode::{ARM7Bootcode, ARM9Bootcode};
+use crate::crc::crc32_update;
+use crate::digest::{Md5, Sha1};
+use crate::error::NdsError;
+use crate::filesystem::{NitroFAT, NitroFNT};
+use crate::header::NDSCartridgeHeader;
+
+const CHUNK_SIZE: u64 = 0x10000;
+
+/* Copies src to dst without the trailing 0xFF padding most ROM images are filled out with,
+and returns the number of bytes written. Refuses to cut into the banner, which is normally
+the last structure before the NitroFS file data. progress, if given, is called with the bytes
+written so far and the total as the copy goes. */
+pub fn trim_rom<R: Read + Seek, W: Write>(
+    src: &mut R,
+    dst: &mut W,
+    progress: Option<&dyn Fn(u64, u64)>,
+) -> Result<u64, NdsError> {
+    let hdr = NDSCartridgeHeader::parse_nds(&mut *src)?;
+    let src_len = src.seek(SeekFrom::End(0))?;
+
+    // Scan backwards for the last byte that isn't padding
+    let mut trimmed_len = 0;
+    let mut chunk = vec![0u8; CHUNK_SIZE as usize];
+    let mut end = src_len;
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK_SIZE);
+        let buf = &mut chunk[..(end - start) as usize];
+        src.seek(SeekFrom::Start(start))?;
+        src.read_exact(buf)?;
+
+        if let Some(pos) = buf.iter().rposition(|&b| b != 0xFF) {
+            trimmed_len = start + pos as u64 + 1;
+            break;
+        }
+        end = start;
+    }
+
+    let banner_offset = hdr.banner_offset as u64;
+    if banner_offset != 0 {
+        src.seek(SeekFrom::Start(banner_offset))?;
+        let banner_end = banner_offset + banner_size(src.read_u16::<LittleEndian>()?) as u64;
+        if trimmed_len < banner_end {
+            return Err(NdsError::UnsupportedFormat(format!(
+                "trimmed size {:#x} would cut into the banner (ends at {:#x})",
+                trimmed_len, banner_end
+            )));
+        }
+    }
+
+    src.seek(SeekFrom::Start(0))?;
+    copy_with_progress(src, dst, trimmed_len, trimmed_len, progress)
+}
+
+/* Copies src to dst, then fills dst with 0xFF up to target_size, or up to the next power of
+two when target_size is None. Returns the number of bytes written. progress is as for
+trim_rom. */
+pub fn pad_rom<R: Read + Seek, W: Write>(
+    src: &mut R,
+    dst: &mut W,
+    target_size: Option<u64>,
+    progress: Option<&dyn Fn(u64, u64)>,
+) -> Result<u64, NdsError> {
+    let src_len = src.seek(SeekFrom::End(0))?;
+
+    let target_size = match target_size {
+        Some(size) if !size.is_power_of_two() => {
+            return Err(NdsError::InvalidInput(format!(
+                "target size {:#x} is not a power of two",
+                size
+            )))
+        }
+        Some(size) if size < src_len => {
+            return Err(NdsError::InvalidInput(format!(
+                "target size {:#x} is smaller than the ROM ({:#x})",
+                size, src_len
+            )))
+        }
+        Some(size) => size,
+        None => src_len.next_power_of_two(),
+    };
+
+    src.seek(SeekFrom::Start(0))?;
+    let mut written = copy_with_progress(src, dst, src_len, target_size, progress)?;
+
+    let padding = vec![0xFFu8; CHUNK_SIZE as usize];
+    while written < target_size {
+        let n = (target_size - written).min(CHUNK_SIZE);
+        dst.write_all(&padding[..n as usize])?;
+        written += n;
+        if let Some(progress) = progress {
+            progress(written, target_size);
+        }
+    }
+
+    Ok(written)
+}
+
+// Copies len bytes from src to dst a chunk at a time, calling progress with the bytes copied
+// so far out of `total` after each chunk. Returns the number of bytes copied.
+fn copy_with_progress<R: Read, W: Write>(
+    src: &mut R,
+    dst: &mut W,
+    len: u64,
+    total: u64,
+    progress: Option<&dyn Fn(u64, u64)>,
+) -> Result<u64, NdsError> {
+    let mut copied = 0;
+    while copied < len {
+        let n = io::copy(&mut src.take((len - copied).min(CHUNK_SIZE)), dst)?;
+        if n == 0 {
+            break;
+        }
+        copied += n;
+        if let Some(progress) = progress {
+            progress(copied, total);
+        }
+    }
+    Ok(copied)
+}
+
+// Feeds the whole of src through f, one chunk at a time.
+fn for_each_chunk<R: Read>(src: &mut R, mut f: impl FnMut(&[u8])) -> Result<(), NdsError> {
+    let mut chunk = vec![0u8; CHUNK_SIZE as usize];
+
+    loop {
+        let n = src.read(&mut chunk)?;
+        if n == 0 {
+            return Ok(());
+        }
+        f(&chunk[..n]);
+    }
+}
+
+// Creates (or truncates) an output ROM at out_path, open for reading and writing.
+pub fn create_rom_file<P: AsRef<Path>>(out_path: P) -> Result<File, NdsError> {
+    Ok(File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(out_path)?)
+}
+
+// Creates out_path as a copy of all of `src`, open for reading and writing.
+pub fn create_rom_copy<R: Read + Seek, P: AsRef<Path>>(
+    src: &mut R,
+    out_path: P,
+) -> Result<File, NdsError> {
+    let mut out = create_rom_file(out_path)?;
+    src.seek(SeekFrom::Start(0))?;
+    io::copy(src, &mut out)?;
+    Ok(out)
+}
+
+// CRC32 of the whole image, as used by No-Intro and similar databases to identify dumps.
+pub fn rom_crc32<R: Read>(src: &mut R) -> Result<u32, NdsError> {
+    let mut crc = 0;
+    for_each_chunk(src, |chunk| crc = crc32_update(crc, chunk))?;
+    Ok(crc)
+}
+
+pub fn rom_sha1<R: Read>(src: &mut R) -> Result<[u8; 20], NdsError> {
+    let mut sha1 = Sha1::new();
+    for_each_chunk(src, |chunk| sha1.update(chunk))?;
+    Ok(sha1.finalize())
+}
+
+pub fn rom_md5<R: Read>(src: &mut R) -> Result<[u8; 16], NdsError> {
+    let mut md5 = Md5::new();
+    for_each_chunk(src, |chunk| md5.update(chunk))?;
+    Ok(md5.finalize())
+}
+
+/* A ROM with its sections parsed: the usual entry point when working with a whole image.
+The bootcodes can be large, so they're only read by load_all or on request; the header,
+banner and file tables are always loaded. */
+pub struct NdsRom {
+    pub header: NDSCartridgeHeader,
+    pub arm9: Option<ARM9Bootcode>, // None until loaded
+    pub arm7: Option<ARM7Bootcode>, // None until loaded
+    pub banner: Option<NDSBanner>,  // None if the ROM has no banner
+    pub fnt: NitroFNT,
+    pub fat: NitroFAT,
+}
+
+impl NdsRom {
+    pub fn load_all<R: Read + Seek>(r: &mut R) -> Result<NdsRom, NdsError> {
+        let mut rom = Self::load_header_only(r)?;
+        rom.load_arm9(r)?;
+        rom.load_arm7(r)?;
+        Ok(rom)
+    }
+
+    // Everything but the ARM9 and ARM7 bootcodes.
+    pub fn load_header_only<R: Read + Seek>(r: &mut R) -> Result<NdsRom, NdsError> {
+        let header = NDSCartridgeHeader::parse_nds(&mut *r)?;
+        let banner = match header.banner_offset {
+            0 => None,
+            _ => Some(NDSBanner::parse(r, &header)?),
+        };
+
+        Ok(NdsRom {
+            arm9: None,
+            arm7: None,
+            banner,
+            fnt: NitroFNT::parse(r, &header)?,
+            fat: NitroFAT::parse(r, &header)?,
+            header,
+        })
+    }
+
+    // Reads the ARM9 bootcode from r, which must be the image the rest was loaded from.
+    pub fn load_arm9<R: Read + Seek>(&mut self, r: &mut R) -> Result<&ARM9Bootcode, NdsError> {
+        Ok(self.arm9.insert(ARM9Bootcode::new(r, &self.header)?))
+    }
+
+    // As load_arm9, for the ARM7.
+    pub fn load_arm7<R: Read + Seek>(&mut self, r: &mut R) -> Result<&ARM7Bootcode, NdsError> {
+        Ok(self.arm7.insert(ARM7Bootcode::new(r, &self.header)?))
+    }
+}
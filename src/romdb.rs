@@ -0,0 +1,66 @@
+// ------------------------------------------------------------
+// Looking up known ROMs by their header codes
+// ------------------------------------------------------------
+
+/* The list is romdb.csv, built into the crate with the rom-db feature. Each row is gamecode,
+makercode, region, revision and name, the codes as the four and two ASCII characters found in
+the header. A code pair may appear once per revision; lookup_rom_db gives the first. */
+
+use once_cell::sync::Lazy;
+
+use crate::header::NdsRegion;
+
+static ROM_DB_CSV: &str = include_str!("../romdb.csv");
+
+static ROM_DB: Lazy<Vec<(u32, u16, RomDbEntry)>> = Lazy::new(|| {
+    ROM_DB_CSV
+        .lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| parse_row(line).unwrap_or_else(|| panic!("bad romdb.csv row: {}", line)))
+        .collect()
+});
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomDbEntry {
+    pub name: &'static str,
+    pub region: NdsRegion,
+    pub revision: u8,
+}
+
+// The entry for a gamecode and makercode, as they are stored in the header.
+pub fn lookup_rom_db(gamecode: u32, makercode: u16) -> Option<RomDbEntry> {
+    ROM_DB
+        .iter()
+        .find(|(g, m, _)| *g == gamecode && *m == makercode)
+        .map(|(_, _, entry)| entry.clone())
+}
+
+fn parse_row(line: &'static str) -> Option<(u32, u16, RomDbEntry)> {
+    // The name comes last, so that it may contain commas
+    let mut fields = line.splitn(5, ',');
+    let gamecode: [u8; 4] = fields.next()?.as_bytes().try_into().ok()?;
+    let makercode: [u8; 2] = fields.next()?.as_bytes().try_into().ok()?;
+    let region = match fields.next()? {
+        "Japan" => NdsRegion::Japan,
+        "USA" => NdsRegion::Usa,
+        "Europe" => NdsRegion::Europe,
+        "Australia" => NdsRegion::Australia,
+        "China" => NdsRegion::China,
+        "Korea" => NdsRegion::Korea,
+        "World" => NdsRegion::World,
+        _ => return None,
+    };
+    let revision = fields.next()?.parse().ok()?;
+    let name = fields.next()?;
+
+    Some((
+        u32::from_le_bytes(gamecode),
+        u16::from_le_bytes(makercode),
+        RomDbEntry {
+            name,
+            region,
+            revision,
+        },
+    ))
+}
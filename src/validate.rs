@@ -0,0 +1,290 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use crate::banner::{banner_size, NDSBanner};
+use crate::crc::bios_get_crc16;
+use crate::error::NdsError;
+use crate::header::NDSCartridgeHeader;
+
+pub use crate::header::LOGO_CRC16;
+
+// The outcome of one check made by validate_rom. For CRCs, `expected` is the value computed
+// from the data and `actual` the one stored in the ROM.
+pub struct ValidationCheck {
+    pub name: String,
+    pub passed: bool,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl ValidationCheck {
+    fn crc(name: &str, expected: u16, actual: u16) -> Self {
+        ValidationCheck {
+            name: name.to_string(),
+            passed: expected == actual,
+            expected: format!("{:#06x}", expected),
+            actual: format!("{:#06x}", actual),
+        }
+    }
+
+    /* Trimmed dumps are smaller than the chip and padded ones fill it exactly; both are fine.
+    Only an image larger than the declared capacity fails. */
+    fn capacity(capacity: u64, rom_len: u64) -> Self {
+        let state = match rom_len.cmp(&capacity) {
+            Ordering::Less => "trimmed",
+            Ordering::Equal => "padded",
+            Ordering::Greater => "larger than the chip",
+        };
+        ValidationCheck {
+            name: "ROM size".to_string(),
+            passed: rom_len <= capacity,
+            expected: format!("<= {:#x}", capacity),
+            actual: format!("{:#x} ({})", rom_len, state),
+        }
+    }
+
+    fn range(name: &str, offset: u32, size: u32, rom_len: u64) -> Self {
+        let end = offset as u64 + size as u64;
+        ValidationCheck {
+            name: name.to_string(),
+            passed: end <= rom_len,
+            expected: format!("end <= {:#x}", rom_len),
+            actual: format!("{:#x}..{:#x}", offset, end),
+        }
+    }
+}
+
+// Two sections the header places over the same bytes, and the range [start, end) they share.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionOverlapWarning {
+    pub first: &'static str,
+    pub second: &'static str,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl fmt::Display for SectionOverlapWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} and {} overlap at {:#x}..{:#x}",
+            self.first, self.second, self.start, self.end
+        )
+    }
+}
+
+/* Finds every pair of sections that overlap, going by the header's offsets and sizes, and for the
+banner by the size of the given version (as read from banner_offset). Empty sections are skipped.
+Garbage between sections is harmless, but overlapping ones mean a corrupt or hand-edited
+header. */
+pub fn check_section_overlap(
+    hdr: &NDSCartridgeHeader,
+    banner_version: u16,
+) -> Vec<SectionOverlapWarning> {
+    let banner_size = if hdr.banner_offset == 0 {
+        0
+    } else {
+        banner_size(banner_version)
+    };
+    let sections: Vec<(&'static str, u64, u64)> = [
+        ("header", 0, 0x200),
+        ("ARM9", hdr.arm9off, hdr.arm9size),
+        ("ARM7", hdr.arm7off, hdr.arm7size),
+        ("FNT", hdr.fnt_offset, hdr.fnt_size),
+        ("FAT", hdr.fat_offset, hdr.fat_size),
+        (
+            "ARM9 overlay table",
+            hdr.arm9_overlay_offset,
+            hdr.arm9_overlay_size,
+        ),
+        (
+            "ARM7 overlay table",
+            hdr.arm7_overlay_offset,
+            hdr.arm7_overlay_size,
+        ),
+        ("banner", hdr.banner_offset, banner_size),
+    ]
+    .into_iter()
+    .filter(|&(_, _, size)| size != 0)
+    .map(|(name, offset, size)| (name, offset as u64, offset as u64 + size as u64))
+    .collect();
+
+    let mut warnings = vec![];
+    for (i, &(first, first_start, first_end)) in sections.iter().enumerate() {
+        for &(second, second_start, second_end) in &sections[i + 1..] {
+            let start = first_start.max(second_start);
+            let end = first_end.min(second_end);
+            if start < end {
+                warnings.push(SectionOverlapWarning {
+                    first,
+                    second,
+                    start,
+                    end,
+                });
+            }
+        }
+    }
+    warnings
+}
+
+/* GBATEK has the ARM9 and ARM7 sizes be multiples of 4, the bootcode being copied to RAM a word
+at a time. (Some loaders also want them 200h-aligned, but retail ROMs don't all follow that, so
+it isn't required here.) Every misaligned size is named in the error. */
+pub fn validate_code_alignment(hdr: &NDSCartridgeHeader) -> Result<(), NdsError> {
+    let misaligned: Vec<String> = [("ARM9", hdr.arm9size), ("ARM7", hdr.arm7size)]
+        .into_iter()
+        .filter(|&(_, size)| size % 4 != 0)
+        .map(|(name, size)| format!("{} size {:#x} is not a multiple of 4", name, size))
+        .collect();
+
+    if misaligned.is_empty() {
+        Ok(())
+    } else {
+        Err(NdsError::InvalidInput(misaligned.join("; ")))
+    }
+}
+
+/* Runs every integrity check that doesn't need the encryption keys: header, logo, secure area
+and banner CRC16s, that each section the header points at lies within the image, that no two
+sections overlap, and that the ARM9 and ARM7 sizes are word-aligned. */
+pub fn validate_rom<R: Read + Seek>(nds: &mut R) -> Result<Vec<ValidationCheck>, NdsError> {
+    let hdr = NDSCartridgeHeader::parse_nds(&mut *nds)?;
+    let rom_len = nds.seek(SeekFrom::End(0))?;
+    let mut checks: Vec<ValidationCheck> = vec![];
+
+    checks.push(ValidationCheck::crc(
+        "Header CRC16",
+        hdr.compute_header_crc16(),
+        hdr.header_crc,
+    ));
+    // Both the logo itself and the CRC16 stored for it must come to CF56h
+    let logo_crc = hdr.logo_crc;
+    checks.push(ValidationCheck {
+        name: "Logo CRC16".to_string(),
+        passed: hdr.is_valid_magic(),
+        expected: format!("{:#06x}", LOGO_CRC16),
+        actual: format!(
+            "{:#06x} (stored {:#06x})",
+            bios_get_crc16(&hdr.logo),
+            logo_crc
+        ),
+    });
+
+    let arm9off = hdr.arm9off;
+    if (0x4000..0x8000).contains(&arm9off) && rom_len >= 0x8000 {
+        let mut secure_area = vec![0u8; (0x8000 - arm9off) as usize];
+        nds.seek(SeekFrom::Start(arm9off as u64))?;
+        nds.read_exact(&mut secure_area)?;
+        checks.push(ValidationCheck::crc(
+            "Secure area CRC16",
+            bios_get_crc16(&secure_area),
+            hdr.secure_area_crc,
+        ));
+    }
+
+    let banner_offset = hdr.banner_offset;
+    let banner_version = if banner_offset != 0 && banner_offset as u64 + 2 <= rom_len {
+        nds.seek(SeekFrom::Start(banner_offset as u64))?;
+        nds.read_u16::<LittleEndian>()?
+    } else {
+        1
+    };
+    let banner_len = banner_size(banner_version);
+    if banner_offset != 0 && banner_offset as u64 + banner_len as u64 <= rom_len {
+        match NDSBanner::parse(&mut *nds, &hdr) {
+            Ok(banner) => {
+                for (i, computed) in banner.computed_crcs().into_iter().enumerate() {
+                    let name = match i {
+                        0 => "Banner CRC16".to_string(),
+                        _ => format!("Banner CRC16 (v{})", i + 1),
+                    };
+                    checks.push(ValidationCheck::crc(&name, computed, banner.crc16[i]));
+                }
+            }
+            // A banner that can't even be parsed has no CRC worth checking, so fail the check
+            Err(e) => checks.push(ValidationCheck {
+                name: "Banner CRC16".to_string(),
+                passed: false,
+                expected: "a readable banner".to_string(),
+                actual: e.to_string(),
+            }),
+        }
+    }
+
+    checks.push(ValidationCheck::capacity(hdr.rom_capacity_bytes(), rom_len));
+    checks.push(ValidationCheck::range(
+        "ARM9 bounds",
+        hdr.arm9off,
+        hdr.arm9size,
+        rom_len,
+    ));
+    checks.push(ValidationCheck::range(
+        "ARM7 bounds",
+        hdr.arm7off,
+        hdr.arm7size,
+        rom_len,
+    ));
+    checks.push(ValidationCheck::range(
+        "FNT bounds",
+        hdr.fnt_offset,
+        hdr.fnt_size,
+        rom_len,
+    ));
+    checks.push(ValidationCheck::range(
+        "FAT bounds",
+        hdr.fat_offset,
+        hdr.fat_size,
+        rom_len,
+    ));
+    checks.push(ValidationCheck::range(
+        "ARM9 overlay table bounds",
+        hdr.arm9_overlay_offset,
+        hdr.arm9_overlay_size,
+        rom_len,
+    ));
+    checks.push(ValidationCheck::range(
+        "ARM7 overlay table bounds",
+        hdr.arm7_overlay_offset,
+        hdr.arm7_overlay_size,
+        rom_len,
+    ));
+    if banner_offset != 0 {
+        checks.push(ValidationCheck::range(
+            "Banner bounds",
+            banner_offset,
+            banner_len,
+            rom_len,
+        ));
+    }
+
+    let alignment = validate_code_alignment(&hdr);
+    checks.push(ValidationCheck {
+        name: "Code size alignment".to_string(),
+        passed: alignment.is_ok(),
+        expected: "multiples of 4".to_string(),
+        actual: match alignment {
+            Ok(()) => format!("{:#x}, {:#x}", { hdr.arm9size }, { hdr.arm7size }),
+            Err(e) => e.to_string(),
+        },
+    });
+
+    let overlaps = check_section_overlap(&hdr, banner_version);
+    checks.push(ValidationCheck {
+        name: "Section overlap".to_string(),
+        passed: overlaps.is_empty(),
+        expected: "no overlapping sections".to_string(),
+        actual: match overlaps.len() {
+            0 => "none".to_string(),
+            _ => overlaps
+                .iter()
+                .map(|o| o.to_string())
+                .collect::<Vec<_>>()
+                .join("; "),
+        },
+    });
+
+    Ok(checks)
+}
@@ -0,0 +1,125 @@
+use std::io::Cursor;
+
+use ndsutils::banner::{BannerLanguage, NDSBanner};
+use ndsutils::crc::bios_get_crc16;
+use ndsutils::header::NDSCartridgeHeader;
+
+fn make_banner_rom() -> Vec<u8> {
+    let mut rom = vec![0u8; 0x1000];
+    let banner = &mut rom[0x400..0xC40];
+    banner[0..2].copy_from_slice(&1u16.to_le_bytes());
+    // Second pixel of the second tile's first row
+    banner[0x20 + 32] = 0x50;
+    for (i, c) in "Test Title".encode_utf16().enumerate() {
+        banner[0x340 + i * 2..0x342 + i * 2].copy_from_slice(&c.to_le_bytes());
+    }
+    rom
+}
+
+#[test]
+fn banner_parses_titles_and_icon() {
+    let rom = make_banner_rom();
+    let hdr = NDSCartridgeHeader {
+        banner_offset: 0x400,
+        ..Default::default()
+    };
+
+    let banner = NDSBanner::parse(&mut Cursor::new(&rom), &hdr).unwrap();
+    assert_eq!(banner.language_count(), 6);
+    assert_eq!(banner.title(BannerLanguage::English), "Test Title");
+    assert_eq!(banner.title(BannerLanguage::Japanese), "");
+    assert!(!banner.has_title(BannerLanguage::Chinese));
+    assert_eq!(banner.title(BannerLanguage::Chinese), "");
+    assert_eq!(banner.icon[9], 5);
+}
+
+#[test]
+fn banner_parse_rejects_missing_banner() {
+    let rom = make_banner_rom();
+    let hdr = NDSCartridgeHeader::default();
+
+    assert!(NDSBanner::parse(&mut Cursor::new(&rom), &hdr).is_err());
+}
+
+#[test]
+fn banner_crc_recompute_and_write_back() {
+    let mut rom = make_banner_rom();
+    let hdr = NDSCartridgeHeader {
+        banner_offset: 0x400,
+        ..Default::default()
+    };
+    let expected = bios_get_crc16(&rom[0x420..0xC40]);
+
+    let mut banner = NDSBanner::parse(&mut Cursor::new(&rom), &hdr).unwrap();
+    assert!(!banner.validate_crc());
+    assert_eq!(banner.computed_crcs(), vec![expected]);
+    banner.recompute_crc();
+    assert!(banner.validate_crc());
+
+    banner.palette[1] = 0x7FFF;
+    banner.write_to(&mut Cursor::new(&mut rom), &hdr).unwrap();
+    let reparsed = NDSBanner::parse(&mut Cursor::new(&rom), &hdr).unwrap();
+    assert!(reparsed.validate_crc());
+    assert_eq!(reparsed.palette[1], 0x7FFF);
+    assert_eq!(reparsed.icon[9], 5);
+    assert_eq!(reparsed.title(BannerLanguage::English), "Test Title");
+}
+
+#[test]
+fn banner_icon_rgba_maps_palette() {
+    let rom = make_banner_rom();
+    let hdr = NDSCartridgeHeader {
+        banner_offset: 0x400,
+        ..Default::default()
+    };
+
+    let mut banner = NDSBanner::parse(&mut Cursor::new(&rom), &hdr).unwrap();
+    banner.palette[5] = 0x001F; // Pure red
+    let rgba = banner.icon_rgba();
+    assert_eq!(rgba.len(), 32 * 32 * 4);
+    assert_eq!(&rgba[9 * 4..10 * 4], &[0xFF, 0, 0, 0xFF]);
+    assert_eq!(rgba[3], 0); // Index 0 is transparent
+}
+
+#[test]
+fn banner_title_strips_bom_and_control_chars() {
+    let rom = make_banner_rom();
+    let hdr = NDSCartridgeHeader {
+        banner_offset: 0x400,
+        ..Default::default()
+    };
+
+    let mut banner = NDSBanner::parse(&mut Cursor::new(&rom), &hdr).unwrap();
+    let raw: Vec<u16> = "\u{FEFF}Line 1\r\nLine\t2".encode_utf16().collect();
+    banner.titles[2][..raw.len()].copy_from_slice(&raw);
+    assert_eq!(banner.title(BannerLanguage::French), "Line 1\nLine2");
+}
+
+#[test]
+fn banner_from_bytes_matches_parse() {
+    let rom = make_banner_rom();
+    let banner = NDSBanner::from_bytes(&rom[0x400..0xC40]).unwrap();
+    assert_eq!(banner.title(BannerLanguage::English), "Test Title");
+    assert_eq!(banner.icon[9], 5);
+}
+
+#[test]
+fn banner_to_bytes_round_trips() {
+    let rom = make_banner_rom();
+    let mut banner = NDSBanner::from_bytes(&rom[0x400..0xC40]).unwrap();
+    banner.palette[5] = 0x7C1F;
+    banner.recompute_crc();
+
+    let bytes = banner.to_bytes();
+    assert_eq!(bytes.len(), 0x840);
+    assert_eq!(NDSBanner::from_bytes(&bytes).unwrap(), banner);
+
+    // Version 2 banners keep their Chinese title
+    banner.version = 2;
+    banner.titles[BannerLanguage::Chinese as usize][0] = 0x4E2D;
+    let bytes = banner.to_bytes();
+    assert_eq!(bytes.len(), 0x940);
+    let reparsed = NDSBanner::from_bytes(&bytes).unwrap();
+    assert!(reparsed.validate_crc());
+    assert_eq!(reparsed.title(BannerLanguage::Chinese), "中");
+}
@@ -0,0 +1,51 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ndsutils::batch::{batch_process, BatchOperation};
+use ndsutils::builder::RomBuilder;
+use ndsutils::header::NDSCartridgeHeader;
+
+// A scratch directory under the target dir, emptied first.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn batch_reports_each_input_separately() {
+    let dir = scratch_dir("batch_reports_each_input_separately");
+
+    let mut hdr = NDSCartridgeHeader::default();
+    hdr.gametitle[..4].copy_from_slice(b"GOOD");
+    let mut image = vec![];
+    RomBuilder::new()
+        .set_header(hdr)
+        .set_arm9(&[0; 0x100])
+        .set_arm7(&[0; 0x100])
+        .build(&mut image)
+        .unwrap();
+    let good = dir.join("good.nds");
+    fs::write(&good, &image).unwrap();
+    let bad = dir.join("bad.nds");
+    fs::write(&bad, [0u8; 0x10]).unwrap();
+
+    let out_dir = dir.join("out");
+    let results = batch_process(
+        &[good.clone(), bad.clone()],
+        BatchOperation::ExtractInfo,
+        &out_dir,
+    )
+    .unwrap();
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].filename, good);
+    assert!(results[0].success);
+    assert!(results[0].summary.starts_with("GOOD"));
+    assert!(!results[1].success);
+    assert!(results[1].summary.contains("too small"));
+
+    let info = fs::read_to_string(out_dir.join("good.nds.txt")).unwrap();
+    assert!(info.contains("title: GOOD\n"));
+}
@@ -0,0 +1,74 @@
+use ndsutils::blz;
+use ndsutils::bootcode::compress_arm9_blz;
+
+// "HDR!" stored, then "abc" x 10 as three literals and two back-references, read from the end:
+// flags 18h, 'c', 'b', 'a', F000h (18 bytes, distance 3), 6000h (9 bytes, distance 3).
+fn sample_image() -> Vec<u8> {
+    let mut data = b"HDR!".to_vec();
+    data.extend_from_slice(&[0x00, 0x60, 0x00, 0xF0, b'a', b'b', b'c', 0x18]);
+    data.extend_from_slice(&(16u32 | (8 << 24)).to_le_bytes());
+    data.extend_from_slice(&14u32.to_le_bytes());
+    data
+}
+
+#[test]
+fn blz_decompresses_literals_and_back_references() {
+    let out = blz::decompress(&sample_image()).unwrap();
+    assert_eq!(&out[..4], b"HDR!");
+    assert_eq!(&out[4..], "abc".repeat(10).as_bytes());
+}
+
+#[test]
+fn blz_rejects_truncated_images() {
+    let mut data = sample_image();
+    data[12] = 0xFF; // Compressed size runs past the start of the image
+    assert!(blz::decompress(&data).is_err());
+    assert!(blz::decompress(&[0u8; 4]).is_err());
+}
+
+#[test]
+fn blz_passes_through_uncompressed_images() {
+    let mut data = b"plain data!!".to_vec();
+    data.extend_from_slice(&[0u8; 4]);
+    assert_eq!(blz::decompress(&data).unwrap(), b"plain data!!");
+}
+
+// Something compressible but not trivially so
+fn sample_code(len: usize) -> Vec<u8> {
+    (0..len as u32)
+        .map(|i| (i.wrapping_mul(2654435761) >> 29) as u8 ^ (i / 64) as u8)
+        .collect()
+}
+
+#[test]
+fn blz_round_trips() {
+    for len in [0x40, 0x1001, 0x9000] {
+        let raw = sample_code(len);
+        let packed = blz::compress(&raw, 0);
+        assert!(
+            packed.len() <= raw.len(),
+            "{:#x} grew to {:#x}",
+            len,
+            packed.len()
+        );
+        assert_eq!(blz::decompress(&packed).unwrap(), raw);
+    }
+}
+
+#[test]
+fn blz_keeps_the_stored_prefix() {
+    let raw = sample_code(0x8000);
+    let packed = compress_arm9_blz(&raw);
+    assert!(packed.len() < raw.len());
+    assert_eq!(packed[..0x4000], raw[..0x4000]);
+    assert_eq!(blz::decompress(&packed).unwrap(), raw);
+}
+
+#[test]
+fn blz_stores_incompressible_data() {
+    let raw: Vec<u8> = (0..0x100u32).map(|i| (i * 167 + 13) as u8).collect();
+    let packed = blz::compress(&raw, 0);
+    assert_eq!(packed.len(), raw.len() + 4);
+    assert_eq!(packed[raw.len()..], [0; 4]);
+    assert_eq!(blz::decompress(&packed).unwrap(), raw);
+}
@@ -0,0 +1,280 @@
+mod common;
+
+use std::io::Cursor;
+
+use ndsutils::bootcode::{
+    analyze_arm9_entry_point, check_secure_area_crc, decompress_arm9_blz, dump_secure_area_header,
+    is_arm9_compressed, ARM7Bootcode, ARM9Bootcode, EntryPointKind, SecureAreaHeader,
+    SECURE_AREA_ID_DECRYPTED,
+};
+use ndsutils::error::NdsError;
+use ndsutils::header::NDSCartridgeHeader;
+
+#[test]
+fn arm7_bootcode_reads_and_dumps_payload() {
+    let mut rom = vec![0u8; 0x1000];
+    let payload: Vec<u8> = (0..0x100).map(|i| i as u8).collect();
+    rom[0x800..0x900].copy_from_slice(&payload);
+
+    let hdr = NDSCartridgeHeader {
+        arm7off: 0x800,
+        arm7size: 0x100,
+        ..Default::default()
+    };
+
+    let arm7 = ARM7Bootcode::new(&mut Cursor::new(&rom), &hdr).unwrap();
+    assert_eq!(arm7.raw_data, payload);
+
+    let mut out = vec![];
+    arm7.dump_to_writer(&mut out).unwrap();
+    assert_eq!(out, payload);
+
+    let hdr = NDSCartridgeHeader {
+        arm7off: 0xF00,
+        arm7size: 0x200,
+        ..Default::default()
+    };
+    assert!(ARM7Bootcode::new(&mut Cursor::new(&rom), &hdr).is_err());
+}
+
+fn arm9_at(arm9off: u32) -> ARM9Bootcode {
+    let mut rom = vec![0u8; 0x9000];
+    rom[arm9off as usize..arm9off as usize + 8].copy_from_slice(b"encryObj");
+
+    let hdr = NDSCartridgeHeader {
+        arm9off,
+        arm9size: 0x800,
+        ..Default::default()
+    };
+
+    ARM9Bootcode::new(&mut Cursor::new(&rom), &hdr).unwrap()
+}
+
+#[test]
+fn arm9_bootcode_accepts_secure_area_offsets() {
+    for arm9off in [0x4000, 0x6000] {
+        let arm9 = arm9_at(arm9off);
+        assert!(arm9.secure_area_present);
+        assert!(arm9.secure_area_encrypted);
+        // arm9size is in bytes
+        assert_eq!(arm9.raw_data.len(), 0x800);
+        assert_eq!(arm9.as_u64_slice().unwrap().len(), 0x100);
+        assert_eq!(arm9.secure_area_id(), Some(*b"encryObj"));
+    }
+}
+
+#[test]
+fn arm9_bootcode_outside_secure_area_is_not_encrypted() {
+    let arm9 = arm9_at(0x8000);
+    assert!(!arm9.secure_area_present);
+    assert!(!arm9.secure_area_encrypted);
+    assert_eq!(arm9.secure_area_id(), None);
+}
+
+#[test]
+fn arm9_bootcode_past_the_end_is_an_error() {
+    let rom = vec![0u8; 0x4400];
+    let hdr = NDSCartridgeHeader {
+        arm9off: 0x4000,
+        arm9size: 0x800,
+        ..Default::default()
+    };
+    assert!(matches!(
+        ARM9Bootcode::from_bytes(&rom, &hdr),
+        Err(NdsError::UnsupportedFormat(_))
+    ));
+}
+
+#[test]
+fn arm9_bootcode_from_in_memory_rom() {
+    let mut rom = vec![0u8; 0x5000];
+    rom[0x20..0x24].copy_from_slice(&0x4000u32.to_le_bytes());
+    rom[0x2C..0x30].copy_from_slice(&0x800u32.to_le_bytes());
+    rom[0x4000..0x4008].copy_from_slice(&SECURE_AREA_ID_DECRYPTED.to_le_bytes());
+
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+    let arm9 = ARM9Bootcode::from_bytes(&rom, &hdr).unwrap();
+    assert!(arm9.secure_area_present);
+    assert!(!arm9.secure_area_encrypted);
+    assert_eq!(arm9.raw_data[..], rom[0x4000..0x4800]);
+}
+
+#[test]
+fn compressed_arm9_is_detected_and_unpacked() {
+    // Module parameters at 0, saying the static module is compressed up to the ARM9's end
+    let mut prefix = vec![0u8; 0x24];
+    prefix[0x14..0x18].copy_from_slice(&(0x0200_0000u32 + 0x34).to_le_bytes());
+    prefix[0x1C..0x24].copy_from_slice(&[0x21, 0x06, 0xC0, 0xDE, 0xDE, 0xC0, 0x06, 0x21]);
+
+    let mut raw_data = prefix.clone();
+    raw_data.extend_from_slice(&[0x00, 0x60, 0x00, 0xF0, b'a', b'b', b'c', 0x18]);
+    raw_data.extend_from_slice(&(16u32 | (8 << 24)).to_le_bytes());
+    raw_data.extend_from_slice(&14u32.to_le_bytes());
+
+    let hdr = NDSCartridgeHeader {
+        arm9raddr: 0x0200_0000,
+        arm9size: raw_data.len() as u32,
+        ..Default::default()
+    };
+    let arm9 = ARM9Bootcode {
+        raw_data,
+        secure_area_present: false,
+        secure_area_encrypted: false,
+    };
+    assert!(is_arm9_compressed(&arm9, &hdr));
+
+    let code = decompress_arm9_blz(&arm9).unwrap();
+    assert_eq!(&code[0x24..], "abc".repeat(10).as_bytes());
+    assert_eq!(code[0x14..0x18], [0; 4]);
+    let unpacked = ARM9Bootcode {
+        raw_data: code,
+        ..arm9
+    };
+    assert!(!is_arm9_compressed(&unpacked, &hdr));
+}
+
+#[test]
+fn secure_area_header_round_trips() {
+    let bytes: Vec<u8> = (0x10..0x20).collect();
+    let header = SecureAreaHeader::parse(&bytes);
+
+    assert_eq!(header.random, [0x1918, 0x1B1A]);
+    assert_eq!(header.crc16_aux, 0x1D1C);
+    assert_eq!(header.crc16, 0x1F1E);
+    assert_eq!(header.as_bytes()[..], bytes[..]);
+}
+
+#[test]
+fn arm9_write_to_refuses_to_grow_unless_allowed() {
+    let mut rom = vec![0u8; 0x8000];
+    rom[0x20..0x24].copy_from_slice(&0x4000u32.to_le_bytes());
+    rom[0x2C..0x30].copy_from_slice(&0x10u32.to_le_bytes());
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+
+    let mut arm9 = ARM9Bootcode::from_bytes(&rom, &hdr).unwrap();
+    arm9.raw_data = vec![0xAB; 0x10];
+    let mut w = Cursor::new(rom.clone());
+    arm9.write_to(&mut w, &hdr, false).unwrap();
+    assert_eq!(w.get_ref()[0x4000..0x4010], [0xAB; 0x10]);
+    assert_eq!(w.get_ref()[0x4010], 0);
+
+    arm9.raw_data = vec![0xCD; 0x20];
+    assert!(matches!(
+        arm9.write_to(&mut w, &hdr, false),
+        Err(NdsError::SizeChanged {
+            expected: 0x10,
+            got: 0x20
+        })
+    ));
+    arm9.write_to(&mut w, &hdr, true).unwrap();
+    assert_eq!(w.get_ref()[0x4000..0x4020], [0xCD; 0x20]);
+}
+
+#[test]
+fn minimal_rom_has_a_decrypted_secure_area() {
+    let rom = common::create_minimal_test_rom();
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+    let arm9 = ARM9Bootcode::new(&mut Cursor::new(&rom), &hdr).unwrap();
+
+    assert!(arm9.secure_area_present);
+    assert!(!arm9.secure_area_encrypted);
+    let secure_header = SecureAreaHeader::parse(&arm9.raw_data);
+    let (crc_ok, _) = check_secure_area_crc(&secure_header, &arm9.raw_data[0x10..]).unwrap();
+    assert!(crc_ok);
+}
+
+#[test]
+fn secure_area_header_dump_depends_on_encryption() {
+    let encrypted = arm9_at(0x4000);
+    assert_eq!(
+        dump_secure_area_header(&encrypted),
+        "000h  Encrypted          65 6E 63 72 79 4F 62 6A 00 00 00 00 00 00 00 00\n"
+    );
+
+    let rom = common::create_minimal_test_rom();
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+    let arm9 = ARM9Bootcode::new(&mut Cursor::new(&rom), &hdr).unwrap();
+    let header = SecureAreaHeader::parse(&arm9.raw_data);
+    let dump = dump_secure_area_header(&arm9);
+    let lines: Vec<&str> = dump.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(
+        lines[0],
+        "000h  Secure area ID     E7FFDEFFE7FFDEFF (decrypted dump)"
+    );
+    assert_eq!(
+        lines[3],
+        format!(
+            "00Eh  CRC16              {:#06x} (actual {:#06x})",
+            header.crc16, header.crc16
+        )
+    );
+
+    assert_eq!(
+        dump_secure_area_header(&arm9_at(0x8000)),
+        "No ARM9 secure area\n"
+    );
+}
+
+#[test]
+fn arm9_entry_point_kinds() {
+    let hdr = NDSCartridgeHeader {
+        arm9entry: 0x0200_0800,
+        arm9raddr: 0x0200_0000,
+        arm9size: 0x1000,
+        ..Default::default()
+    };
+    let entry_with = |words: &[u32]| {
+        let mut raw_data = vec![0u8; 0x1000];
+        for (i, w) in words.iter().enumerate() {
+            raw_data[0x800 + i * 4..0x804 + i * 4].copy_from_slice(&w.to_le_bytes());
+        }
+        let arm9 = ARM9Bootcode {
+            raw_data,
+            secure_area_present: false,
+            secure_area_encrypted: false,
+        };
+        analyze_arm9_entry_point(&arm9, &hdr)
+    };
+
+    // b +0x100 and b -8 (to itself)
+    assert_eq!(
+        entry_with(&[0xEA00_003E]),
+        EntryPointKind::DirectBranch {
+            target: 0x0200_0900
+        }
+    );
+    assert_eq!(
+        entry_with(&[0xEAFF_FFFE]),
+        EntryPointKind::DirectBranch {
+            target: 0x0200_0800
+        }
+    );
+    // ldr pc, [pc, #0] with its literal two words on
+    assert_eq!(
+        entry_with(&[0xE59F_F000, 0, 0x0200_0C00]),
+        EntryPointKind::DirectBranch {
+            target: 0x0200_0C00
+        }
+    );
+    assert_eq!(
+        entry_with(&[0x0200_0A00, 0x0200_0B00]),
+        EntryPointKind::PointerTable
+    );
+    // mov r12, #0x04000000, as at the start of the usual crt0
+    assert_eq!(entry_with(&[0xE3A0_C301]), EntryPointKind::Unknown);
+
+    let outside = NDSCartridgeHeader {
+        arm9entry: 0x0100_0000,
+        ..hdr
+    };
+    let arm9 = ARM9Bootcode {
+        raw_data: vec![0; 0x1000],
+        secure_area_present: false,
+        secure_area_encrypted: false,
+    };
+    assert_eq!(
+        analyze_arm9_entry_point(&arm9, &outside),
+        EntryPointKind::Unknown
+    );
+}
@@ -0,0 +1,312 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use ndsutils::banner::NDSBanner;
+use ndsutils::builder::RomBuilder;
+use ndsutils::error::NdsError;
+use ndsutils::filesystem::{
+    compact_nitro_fs, delete_file, inject_file, read_entry, FATEntry, NitroFNT, NitroFS,
+};
+use ndsutils::header::NDSCartridgeHeader;
+
+fn build(builder: &RomBuilder) -> Vec<u8> {
+    let mut rom = vec![];
+    builder.build(&mut rom).unwrap();
+    rom
+}
+
+#[test]
+fn built_rom_parses_back() {
+    let mut hdr = NDSCartridgeHeader::default();
+    hdr.gametitle.copy_from_slice(b"HOMEBREW\0\0\0\0");
+    let arm9: Vec<u8> = (0..0x1234u32).map(|i| i as u8).collect();
+    let arm7 = vec![0x77; 0x324];
+
+    let rom = build(
+        RomBuilder::new()
+            .set_header(hdr)
+            .set_arm9(&arm9)
+            .set_arm7(&arm7)
+            .add_file("data/b.bin", &[1, 2, 3])
+            .add_file("a.txt", b"hello")
+            .add_file("data/sub/c.bin", &[]),
+    );
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+
+    assert_eq!(hdr.game_title(), "HOMEBREW");
+    assert!(hdr.validate_header_crc16());
+    assert_eq!({ hdr.arm9off }, 0x4000);
+    assert_eq!(&rom[0x4000..0x4000 + arm9.len()], &arm9[..]);
+    let arm7off = hdr.arm7off as usize;
+    assert_eq!(arm7off % 0x200, 0);
+    assert_eq!(&rom[arm7off..arm7off + arm7.len()], &arm7[..]);
+    assert_eq!({ hdr.total_used_rom_size } as usize, rom.len());
+    assert!(hdr.rom_capacity_bytes() >= rom.len() as u64);
+
+    let mut cursor = Cursor::new(&rom);
+    let nitro = NitroFS::parse(&mut cursor, &hdr).unwrap();
+    let files: Vec<(PathBuf, Vec<u8>)> = nitro
+        .fnt
+        .iter()
+        .map(|(path, id)| {
+            (
+                path,
+                read_entry(&mut cursor, &nitro.fat.0[id as usize]).unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        files,
+        vec![
+            (PathBuf::from("a.txt"), b"hello".to_vec()),
+            (PathBuf::from("data/b.bin"), vec![1, 2, 3]),
+            (PathBuf::from("data/sub/c.bin"), vec![]),
+        ]
+    );
+}
+
+#[test]
+fn built_banner_gets_fresh_crcs() {
+    let mut banner = vec![0u8; 0x840];
+    banner[0] = 1;
+    banner[0x240..0x242].copy_from_slice(&u16::from(b'T').to_le_bytes());
+
+    let rom = build(
+        RomBuilder::new()
+            .set_arm9(&[0; 0x100])
+            .set_arm7(&[0; 0x100])
+            .set_banner(&banner),
+    );
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+
+    let parsed = NDSBanner::parse(&mut Cursor::new(&rom), &hdr).unwrap();
+    assert!(parsed.validate_crc());
+}
+
+#[test]
+fn build_needs_both_bootcodes() {
+    let mut rom = vec![];
+    assert!(RomBuilder::new().set_arm9(&[0; 4]).build(&mut rom).is_err());
+}
+
+#[test]
+fn build_rejects_code_sizes_that_are_not_word_multiples() {
+    let mut rom = vec![];
+    let err = RomBuilder::new()
+        .set_arm9(&[0; 0x101])
+        .set_arm7(&[0; 0x102])
+        .build(&mut rom)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid input: ARM9 size 0x101 is not a multiple of 4; \
+         ARM7 size 0x102 is not a multiple of 4"
+    );
+    assert!(rom.is_empty());
+}
+
+#[test]
+fn fnt_round_trips_and_rejects_duplicates() {
+    let fnt = NitroFNT::from_paths(&["x/y/z.bin", "x/a.bin", "top.bin"], 5).unwrap();
+    let ids: Vec<(PathBuf, u16)> = fnt.iter().collect();
+    assert_eq!(
+        ids,
+        vec![
+            (PathBuf::from("top.bin"), 5),
+            (PathBuf::from("x/a.bin"), 6),
+            (PathBuf::from("x/y/z.bin"), 7),
+        ]
+    );
+
+    assert!(NitroFNT::from_paths(&["a", "a"], 0).is_err());
+    assert!(NitroFNT::from_paths(&["../a"], 0).is_err());
+}
+
+#[test]
+fn injected_files_are_appended_and_renumber_later_ids() {
+    let mut rom = build(
+        RomBuilder::new()
+            .set_arm9(&[9; 0x100])
+            .set_arm7(&[7; 0x100])
+            .add_file("a.txt", b"hello")
+            .add_file("data/b.bin", &[1, 2, 3])
+            .add_file("data/sub/c.bin", &[4]),
+    );
+    let original_len = rom.len();
+    let mut hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+    let NitroFS { mut fnt, mut fat } = NitroFS::parse(&mut Cursor::new(&rom), &hdr).unwrap();
+
+    // data/ comes before data/sub/ in ID order, so c.bin moves up one
+    let id = inject_file(&mut rom, &mut hdr, &mut fat, &mut fnt, "data/x.bin", b"new").unwrap();
+    assert_eq!(id, 2);
+    let id = inject_file(
+        &mut rom,
+        &mut hdr,
+        &mut fat,
+        &mut fnt,
+        "more/d.bin",
+        &[5; 0x300],
+    )
+    .unwrap();
+    assert_eq!(id, 4);
+    assert!(inject_file(&mut rom, &mut hdr, &mut fat, &mut fnt, "a.txt", &[]).is_err());
+    assert!(rom.len() > original_len);
+
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+    assert!(hdr.validate_header_crc16());
+    assert_eq!({ hdr.total_used_rom_size } as usize, rom.len());
+    assert!(hdr.rom_capacity_bytes() >= rom.len() as u64);
+
+    let mut cursor = Cursor::new(&rom);
+    let nitro = NitroFS::parse(&mut cursor, &hdr).unwrap();
+    assert_eq!(nitro.fat.0.len(), 5);
+    let files: Vec<(PathBuf, u16, Vec<u8>)> = nitro
+        .fnt
+        .iter()
+        .map(|(path, id)| {
+            let data = read_entry(&mut cursor, &nitro.fat.0[id as usize]).unwrap();
+            (path, id, data)
+        })
+        .collect();
+    assert_eq!(
+        files,
+        vec![
+            (PathBuf::from("a.txt"), 0, b"hello".to_vec()),
+            (PathBuf::from("data/b.bin"), 1, vec![1, 2, 3]),
+            (PathBuf::from("data/x.bin"), 2, b"new".to_vec()),
+            (PathBuf::from("data/sub/c.bin"), 3, vec![4]),
+            (PathBuf::from("more/d.bin"), 4, vec![5; 0x300]),
+        ]
+    );
+}
+
+#[test]
+fn compaction_drops_the_holes_left_by_deleted_files() {
+    let rom = build(
+        RomBuilder::new()
+            .set_arm9(&[9; 0x100])
+            .set_arm7(&[7; 0x100])
+            .add_file("a.bin", &[1; 0x10])
+            .add_file("b.bin", &[2; 0x4000])
+            .add_file("c.bin", &[3; 0x20]),
+    );
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+    let NitroFS { mut fnt, mut fat } = NitroFS::parse(&mut Cursor::new(&rom), &hdr).unwrap();
+    delete_file(&mut fat, &mut fnt, 1).unwrap();
+
+    let mut out = vec![];
+    let new_hdr = compact_nitro_fs(&mut Cursor::new(&rom), &mut out, &hdr, &fat).unwrap();
+    assert_eq!({ new_hdr.total_used_rom_size } as usize, out.len());
+    assert_eq!(out.len(), rom.len() - 0x4000);
+    assert_eq!(&out[0x4000..0x4100], &[9; 0x100]);
+
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&out).unwrap();
+    assert!(hdr.validate_header_crc16());
+    let mut cursor = Cursor::new(&out);
+    let fat = NitroFS::parse(&mut cursor, &hdr).unwrap().fat;
+    // c.bin keeps its ID, with its data moved down into b.bin's place
+    assert_eq!(read_entry(&mut cursor, &fat.0[0]).unwrap(), vec![1; 0x10]);
+    assert!(fat.0[1].is_empty());
+    assert_eq!(read_entry(&mut cursor, &fat.0[2]).unwrap(), vec![3; 0x20]);
+    assert_eq!(fat.0[2].start, fat.0[0].start + 0x200);
+}
+
+#[test]
+fn compaction_refuses_to_move_files_over_a_larger_banner() {
+    let mut banner = vec![0u8; 0x840];
+    banner[0] = 1;
+    let mut rom = build(
+        RomBuilder::new()
+            .set_arm9(&[9; 0x100])
+            .set_arm7(&[7; 0x100])
+            .set_banner(&banner)
+            .add_file("a.bin", &[1; 0x10]),
+    );
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+    let fat = NitroFS::parse(&mut Cursor::new(&rom), &hdr).unwrap().fat;
+    let mut out = vec![];
+    compact_nitro_fs(&mut Cursor::new(&rom), &mut out, &hdr, &fat).unwrap();
+
+    // As a version 3 banner, it is A40h long and runs into the file right after it
+    let banner_offset = hdr.banner_offset as usize;
+    rom[banner_offset] = 3;
+    assert!(matches!(
+        compact_nitro_fs(&mut Cursor::new(&rom), &mut vec![], &hdr, &fat),
+        Err(NdsError::UnsupportedFormat(_))
+    ));
+}
+
+#[test]
+fn compaction_keeps_files_placed_before_the_arm7() {
+    let mut rom = build(
+        RomBuilder::new()
+            .set_arm9(&[9; 0x400])
+            .set_arm7(&[7; 0x100])
+            .add_file("a.bin", &[1; 0x10])
+            .add_file("b.bin", &[2; 0x4000])
+            .add_file("c.bin", &[3; 0x20]),
+    );
+    let mut hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+    let NitroFS { mut fnt, mut fat } = NitroFS::parse(&mut Cursor::new(&rom), &hdr).unwrap();
+
+    // As in retail ROMs, the first file is an overlay placed between the ARM9 and the ARM7
+    hdr.arm9size = 0x100;
+    rom[0x4200..0x4210].copy_from_slice(&[0xA; 0x10]);
+    fat.0[0] = FATEntry {
+        start: 0x4200,
+        end: 0x4210,
+    };
+    delete_file(&mut fat, &mut fnt, 1).unwrap();
+
+    let mut out = vec![];
+    let new_hdr = compact_nitro_fs(&mut Cursor::new(&rom), &mut out, &hdr, &fat).unwrap();
+    assert_eq!({ new_hdr.total_used_rom_size } as usize, out.len());
+    assert!(out.len() < rom.len() - 0x3000);
+
+    let mut cursor = Cursor::new(&out);
+    let fat = NitroFS::parse(&mut cursor, &new_hdr).unwrap().fat;
+    assert_eq!((fat.0[0].start, fat.0[0].end), (0x4200, 0x4210));
+    assert_eq!(read_entry(&mut cursor, &fat.0[0]).unwrap(), vec![0xA; 0x10]);
+    assert!(fat.0[1].is_empty());
+    assert_eq!(read_entry(&mut cursor, &fat.0[2]).unwrap(), vec![3; 0x20]);
+}
+
+#[test]
+fn compaction_moves_the_fnt_and_fat_left_after_injected_files() {
+    let mut rom = build(
+        RomBuilder::new()
+            .set_arm9(&[9; 0x100])
+            .set_arm7(&[7; 0x100])
+            .add_file("a.bin", &[1; 0x10])
+            .add_file("b.bin", &[2; 0x4000]),
+    );
+    let mut hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+    let NitroFS { mut fnt, mut fat } = NitroFS::parse(&mut Cursor::new(&rom), &hdr).unwrap();
+    inject_file(&mut rom, &mut hdr, &mut fat, &mut fnt, "c.bin", &[3; 0x20]).unwrap();
+    delete_file(&mut fat, &mut fnt, 1).unwrap();
+    let old_fat_offset = hdr.fat_offset;
+
+    let mut out = vec![];
+    let new_hdr = compact_nitro_fs(&mut Cursor::new(&rom), &mut out, &hdr, &fat).unwrap();
+    assert_eq!({ new_hdr.total_used_rom_size } as usize, out.len());
+    assert!(out.len() < rom.len() - 0x3000);
+    assert!(new_hdr.fat_offset < old_fat_offset);
+
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&out).unwrap();
+    assert!(hdr.validate_header_crc16());
+    let mut cursor = Cursor::new(&out);
+    let NitroFS { fnt, fat } = NitroFS::parse(&mut cursor, &hdr).unwrap();
+    let a = fnt.find_by_path(Path::new("a.bin")).unwrap();
+    let c = fnt.find_by_path(Path::new("c.bin")).unwrap();
+    // b.bin sits between the other two, so it stays as an empty file
+    let b = fnt.find_by_path(Path::new("b.bin")).unwrap();
+    assert!(fat.0[b as usize].is_empty());
+    assert_eq!(
+        read_entry(&mut cursor, &fat.0[a as usize]).unwrap(),
+        vec![1; 0x10]
+    );
+    assert_eq!(
+        read_entry(&mut cursor, &fat.0[c as usize]).unwrap(),
+        vec![3; 0x20]
+    );
+}
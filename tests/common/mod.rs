@@ -0,0 +1,29 @@
+// Fixtures shared by the integration tests. Being under tests/, none of this is ever part of
+// the library build.
+
+use ndsutils::bootcode::{SecureAreaHeader, SECURE_AREA_ID_DECRYPTED};
+use ndsutils::builder::RomBuilder;
+use ndsutils::crc::bios_get_crc16;
+
+/* The smallest ROM the parsers accept: a header with every CRC16 filled in, a 800h-byte ARM9
+that is a zeroed, already-decrypted secure area with a correct secure area CRC, a 4-byte ARM7
+stub, an FNT holding only the root directory, an empty FAT and no banner. The logo is left
+blank, so the logo CRC16 is consistent with it but isn't Nintendo's. */
+pub fn create_minimal_test_rom() -> Vec<u8> {
+    let mut arm9 = vec![0u8; 0x800];
+    let header = SecureAreaHeader {
+        id: SECURE_AREA_ID_DECRYPTED,
+        random: [0; 2],
+        crc16_aux: 0,
+        crc16: bios_get_crc16(&arm9[0x10..]),
+    };
+    arm9[..SecureAreaHeader::SIZE].copy_from_slice(&header.as_bytes());
+
+    let mut rom = vec![];
+    RomBuilder::new()
+        .set_arm9(&arm9)
+        .set_arm7(&[0x1E, 0xFF, 0x2F, 0xE1]) // bx lr
+        .build(&mut rom)
+        .unwrap();
+    rom
+}
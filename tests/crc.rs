@@ -0,0 +1,69 @@
+use std::hash::Hasher;
+use std::io::{self, Cursor};
+
+use ndsutils::crc::{
+    bios_get_crc16, crc16_ccitt, crc32_iso, crc32_update, generate_crc16_table, Crc16Hasher,
+};
+
+#[test]
+fn crc16_of_empty_is_initial_value() {
+    assert_eq!(bios_get_crc16(&[]), 0xFFFF);
+}
+
+#[test]
+fn crc16_matches_modbus_check_value() {
+    // The BIOS CRC16 is CRC-16/MODBUS; "123456789" is the standard check input.
+    assert_eq!(bios_get_crc16(b"123456789"), 0x4B37);
+}
+
+#[test]
+fn generated_crc16_table_drives_the_bios_crc() {
+    // A bytewise CRC straight from the table gives the same result as bios_get_crc16
+    let table = generate_crc16_table();
+    let crc = b"123456789".iter().fold(0xFFFFu16, |crc, &b| {
+        (crc >> 8) ^ table[((crc as u8) ^ b) as usize]
+    });
+    assert_eq!(crc, bios_get_crc16(b"123456789"));
+}
+
+#[test]
+fn streamed_crc16_matches_the_whole_buffer() {
+    let data: Vec<u8> = (0..0x7F0u32).map(|i| (i * 31 + i / 7) as u8).collect();
+    let expected = bios_get_crc16(&data);
+
+    let mut hasher = Crc16Hasher::new();
+    assert_eq!(hasher.finalize(), 0xFFFF);
+    for chunk in data.chunks(0x100) {
+        hasher.update(chunk);
+    }
+    assert_eq!(hasher.finalize(), expected);
+    assert_eq!(hasher.finish(), expected as u64);
+
+    let mut hasher = Crc16Hasher::default();
+    io::copy(&mut Cursor::new(&data), &mut hasher).unwrap();
+    assert_eq!(hasher.finalize(), expected);
+}
+
+#[test]
+fn crc32_matches_iso_hdlc_check_value() {
+    assert_eq!(crc32_update(0, b"123456789"), 0xCBF43926);
+    // Feeding the data in pieces gives the same result
+    assert_eq!(crc32_update(crc32_update(0, b"1234"), b"56789"), 0xCBF43926);
+}
+
+#[test]
+fn crc32_iso_check_values() {
+    assert_eq!(crc32_iso(&[]), 0);
+    assert_eq!(crc32_iso(b"123456789"), 0xCBF43926);
+    assert_eq!(
+        crc32_iso(b"The quick brown fox jumps over the lazy dog"),
+        0x414FA339
+    );
+}
+
+#[test]
+fn crc16_ccitt_check_values() {
+    assert_eq!(crc16_ccitt(&[]), 0xFFFF);
+    assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+    assert_ne!(crc16_ccitt(b"123456789"), bios_get_crc16(b"123456789"));
+}
@@ -0,0 +1,270 @@
+use std::fs::File;
+use std::io::Cursor;
+
+use ndsutils::bootcode::{
+    check_secure_area_crc, dump_secure_area_header, is_arm9_compressed, ARM9Bootcode,
+    SecureAreaHeader, SECURE_AREA_ID_DECRYPTED,
+};
+use ndsutils::builder::RomBuilder;
+use ndsutils::crypto::{
+    apply_keycode, blowfish_nds, bytes_as_u64_slice, decrypt_secure_area, dump_kbuf,
+    encrypt_secure_area, init_keycode, init_keycode_traced, key2_seed, load_encr_data,
+    u64_slice_as_bytes, verify_secure_area_magic, KeyLevel, SecureAreaKey,
+};
+use ndsutils::error::NdsError;
+use ndsutils::header::NDSCartridgeHeader;
+
+#[test]
+fn load_encr_data_reads_whole_table() {
+    let mut f = File::open("encr_data.bin").unwrap();
+    let encr = load_encr_data(&mut f).unwrap();
+
+    assert_eq!(encr.len(), 1042);
+    assert_ne!(encr[0], 0);
+}
+
+#[test]
+fn load_encr_data_rejects_wrong_files() {
+    let short = vec![0u8; 100];
+    assert!(matches!(
+        load_encr_data(&mut Cursor::new(short)),
+        Err(NdsError::InvalidEncrData(_))
+    ));
+
+    let zeros = vec![0u8; 1042 * 4];
+    assert!(matches!(
+        load_encr_data(&mut Cursor::new(zeros)),
+        Err(NdsError::InvalidEncrData(_))
+    ));
+}
+
+#[test]
+fn blowfish_round_trips() {
+    let mut f = File::open("encr_data.bin").unwrap();
+    let encr = load_encr_data(&mut f).unwrap();
+
+    let plain = 0x0123_4567_89AB_CDEFu64;
+    let mut v = plain;
+    blowfish_nds(&mut v, &encr, true);
+    assert_ne!(v, plain);
+    blowfish_nds(&mut v, &encr, false);
+    assert_eq!(v, plain);
+}
+
+#[test]
+fn secure_area_encryption_round_trips() {
+    let mut f = File::open("encr_data.bin").unwrap();
+    let encr = load_encr_data(&mut f).unwrap();
+    let gamecode = u32::from_le_bytes(*b"ADAE");
+
+    let mut raw_data: Vec<u8> = (0..0x1000u32).map(|i| (i * 7 + i / 255) as u8).collect();
+    raw_data[..8].copy_from_slice(&SECURE_AREA_ID_DECRYPTED.to_le_bytes());
+    let mut arm9 = ARM9Bootcode {
+        raw_data: raw_data.clone(),
+        secure_area_present: true,
+        secure_area_encrypted: false,
+    };
+
+    encrypt_secure_area(&mut arm9, &encr, gamecode);
+    assert!(arm9.secure_area_encrypted);
+    assert_ne!(arm9.raw_data[..0x800], raw_data[..0x800]);
+    assert_eq!(arm9.raw_data[0x800..], raw_data[0x800..]);
+
+    decrypt_secure_area(&mut arm9, &encr, gamecode, false).unwrap();
+    assert!(!arm9.secure_area_encrypted);
+    assert_eq!(arm9.raw_data[..8], SECURE_AREA_ID_DECRYPTED.to_le_bytes());
+    assert_eq!(arm9.raw_data[0x10..], raw_data[0x10..]);
+
+    assert!(verify_secure_area_magic(&arm9));
+
+    // The only plaintext change is the recomputed CRC16 at 0x0E
+    let header = SecureAreaHeader::parse(&arm9.raw_data);
+    assert!(
+        check_secure_area_crc(&header, &arm9.raw_data[0x10..0x800])
+            .unwrap()
+            .0
+    );
+}
+
+#[test]
+fn decrypting_with_the_wrong_gamecode_fails() {
+    let mut f = File::open("encr_data.bin").unwrap();
+    let encr = load_encr_data(&mut f).unwrap();
+
+    let mut raw_data = vec![0u8; 0x1000];
+    raw_data[..8].copy_from_slice(&SECURE_AREA_ID_DECRYPTED.to_le_bytes());
+    let mut arm9 = ARM9Bootcode {
+        raw_data,
+        secure_area_present: true,
+        secure_area_encrypted: false,
+    };
+    encrypt_secure_area(&mut arm9, &encr, u32::from_le_bytes(*b"ADAE"));
+    let encrypted = arm9.raw_data.clone();
+
+    let result = decrypt_secure_area(&mut arm9, &encr, u32::from_le_bytes(*b"APAE"), false);
+    assert!(matches!(result, Err(NdsError::InvalidMagic)));
+    assert!(arm9.secure_area_encrypted);
+    assert_eq!(arm9.raw_data, encrypted);
+
+    // encr_data.bin is only read, so retrying with the right gamecode works
+    let mut retried = ARM9Bootcode {
+        raw_data: encrypted.clone(),
+        secure_area_present: true,
+        secure_area_encrypted: true,
+    };
+    let key =
+        decrypt_secure_area(&mut retried, &encr, u32::from_le_bytes(*b"ADAE"), false).unwrap();
+    assert!(verify_secure_area_magic(&retried));
+    assert_eq!(
+        key.0,
+        SecureAreaKey::for_gamecode(u32::from_le_bytes(*b"ADAE"), &encr).0
+    );
+
+    // Forced, the (garbage) result is kept
+    decrypt_secure_area(&mut arm9, &encr, u32::from_le_bytes(*b"APAE"), true).unwrap();
+    assert!(!arm9.secure_area_encrypted);
+    assert!(!verify_secure_area_magic(&arm9));
+    assert_ne!(arm9.raw_data, encrypted);
+}
+
+#[test]
+fn init_keycode_matches_successive_apply_keycode() {
+    let mut f = File::open("encr_data.bin").unwrap();
+    let encr = load_encr_data(&mut f).unwrap();
+    let gamecode = u32::from_le_bytes(*b"ADAE");
+
+    let mut kbuf = encr;
+    let mut keycode = [gamecode, gamecode >> 1, gamecode << 1];
+    apply_keycode(&mut keycode, &mut kbuf);
+    assert_eq!(init_keycode(&encr, gamecode, KeyLevel::Level1), kbuf);
+    apply_keycode(&mut keycode, &mut kbuf);
+    assert_eq!(init_keycode(&encr, gamecode, KeyLevel::Level2), kbuf);
+    keycode[1] <<= 1;
+    keycode[2] >>= 1;
+    apply_keycode(&mut keycode, &mut kbuf);
+    assert_eq!(init_keycode(&encr, gamecode, KeyLevel::Level3), kbuf);
+}
+
+#[test]
+fn traced_key_schedule_dumps_every_step() {
+    let mut f = File::open("encr_data.bin").unwrap();
+    let encr = load_encr_data(&mut f).unwrap();
+    let gamecode = u32::from_le_bytes(*b"ADAE");
+
+    let mut steps = vec![];
+    let kbuf = init_keycode_traced(&encr, gamecode, KeyLevel::Level3, |step, kbuf| {
+        steps.push((step, kbuf.to_vec()))
+    });
+    assert_eq!(kbuf, init_keycode(&encr, gamecode, KeyLevel::Level3));
+    assert_eq!(steps.iter().map(|s| s.0).collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(steps[1].1, init_keycode(&encr, gamecode, KeyLevel::Level2));
+
+    let mut out = vec![];
+    dump_kbuf(&encr, &mut out).unwrap();
+    let dump = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = dump.lines().collect();
+    // 3 P-array lines and 32 per S-box, each section under a label
+    assert_eq!(lines.len(), 5 + 3 + 4 * 32);
+    assert_eq!(lines[0], "P-array:");
+    assert!(lines[1].starts_with("  000: 5f20d599 b9f54457 d9a4196e 945a6a9e"));
+    // The last P-array line holds only words 10h and 11h
+    assert_eq!(lines[3].split_whitespace().count(), 3);
+    assert_eq!(lines[4], "S-box 0:");
+    assert!(lines[5].starts_with("  012: "));
+}
+
+#[test]
+fn u64_byte_views_check_alignment_and_length() {
+    let words = [0x0807_0605_0403_0201u64, u64::MAX];
+    let bytes = u64_slice_as_bytes(&words);
+    assert_eq!(bytes.len(), 16);
+    assert_eq!(bytes_as_u64_slice(bytes).unwrap(), &words);
+
+    assert!(bytes_as_u64_slice(&bytes[1..9]).is_err());
+    assert!(bytes_as_u64_slice(&bytes[..12]).is_err());
+}
+
+#[test]
+fn key2_seed_rejects_out_of_range_selects() {
+    assert_eq!(key2_seed(0).unwrap(), 0xE8);
+    assert_eq!(key2_seed(7).unwrap(), 0xD5);
+    assert!(key2_seed(8).is_err());
+}
+
+#[test]
+fn apply_keycode_matches_recorded_output() {
+    // A regression vector recorded from the earlier implementation, which viewed tk[0..2] and
+    // tk[1..3] as u64s. It only pins the current behaviour; it isn't from GBATEK or a real ROM.
+    let mut f = File::open("encr_data.bin").unwrap();
+    let mut kbuf = load_encr_data(&mut f).unwrap();
+    let gamecode = u32::from_le_bytes(*b"ADAE");
+    let mut keycode = [gamecode, gamecode >> 1, gamecode << 1];
+
+    apply_keycode(&mut keycode, &mut kbuf);
+    assert_eq!(keycode, [0xAFE9ADF7, 0xD3FD86BD, 0x3FD6AB5E]);
+    assert_eq!(kbuf[0], 0x93F8BD67);
+    assert_eq!(kbuf[0x11], 0x2CA93E39);
+    assert_eq!(kbuf[0x411], 0xE44A41FE);
+}
+
+#[test]
+fn secure_area_keys_match_recorded_ciphertext() {
+    // Regression vectors recorded from this implementation, not taken from GBATEK or real ROMs
+    let mut f = File::open("encr_data.bin").unwrap();
+    let encr = load_encr_data(&mut f).unwrap();
+    let samples = [
+        (
+            b"ASME",
+            0x85E25F8D,
+            0x281FD33D285E7008u64,
+            0xF34BAAE69075AE2Bu64,
+        ),
+        (
+            b"ADAE",
+            0x10BBB5C0,
+            0x71DC31D0E81E8355u64,
+            0x3952C629B2C83198u64,
+        ),
+    ];
+
+    for (gamecode, p0, encrypted_id, encrypted_zero) in samples {
+        let key = SecureAreaKey::for_gamecode(u32::from_le_bytes(*gamecode), &encr);
+        assert_eq!(key.0[0], p0);
+
+        let mut block = u64::from_le_bytes(*b"encryObj");
+        key.encrypt(&mut block);
+        assert_eq!(block, encrypted_id);
+        key.decrypt(&mut block);
+        assert_eq!(block.to_le_bytes(), *b"encryObj");
+
+        let mut block = 0;
+        key.encrypt(&mut block);
+        assert_eq!(block, encrypted_zero);
+    }
+}
+
+// Regression test: an ARM9 under 800h bytes (e.g. after replace-arm9 with a small binary) made
+// the secure area code slice past its end. It must be treated as having no secure area.
+#[test]
+fn arm9_shorter_than_a_secure_area_has_none() {
+    let mut rom = vec![];
+    RomBuilder::new()
+        .set_arm9(&[0xFF; 0x100])
+        .set_arm7(&[0x1E, 0xFF, 0x2F, 0xE1]) // bx lr
+        .build(&mut rom)
+        .unwrap();
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+    assert_eq!({ hdr.arm9off }, 0x4000);
+
+    let mut arm9 = ARM9Bootcode::new(&mut Cursor::new(&rom), &hdr).unwrap();
+    assert!(!arm9.secure_area_present);
+    assert_eq!(arm9.secure_area_id(), None);
+    assert_eq!(dump_secure_area_header(&arm9), "No ARM9 secure area\n");
+    assert!(!is_arm9_compressed(&arm9, &hdr));
+
+    let mut f = File::open("encr_data.bin").unwrap();
+    let encr = load_encr_data(&mut f).unwrap();
+    decrypt_secure_area(&mut arm9, &encr, hdr.gamecode, true).unwrap();
+    encrypt_secure_area(&mut arm9, &encr, hdr.gamecode);
+    assert_eq!(arm9.raw_data, [0xFF; 0x100]);
+}
@@ -0,0 +1,41 @@
+use std::io::Cursor;
+
+use ndsutils::digest::{to_hex, Md5, Sha1};
+use ndsutils::rom::{rom_md5, rom_sha1};
+
+#[test]
+fn sha1_matches_fips_vectors() {
+    assert_eq!(
+        to_hex(&rom_sha1(&mut Cursor::new(b"abc")).unwrap()),
+        "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+    assert_eq!(
+        to_hex(&Sha1::new().finalize()),
+        "da39a3ee5e6b4b0d3255bfef95601890afd80709"
+    );
+
+    let mut sha1 = Sha1::new();
+    sha1.update(b"abcdbcdecdefdefgefghfghighij");
+    sha1.update(b"hijkijkljklmklmnlmnomnopnopq");
+    assert_eq!(
+        to_hex(&sha1.finalize()),
+        "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+    );
+}
+
+#[test]
+fn md5_matches_rfc1321_vectors() {
+    assert_eq!(
+        to_hex(&rom_md5(&mut Cursor::new(b"abc")).unwrap()),
+        "900150983cd24fb0d6963f7d28e17f72"
+    );
+    assert_eq!(
+        to_hex(&Md5::new().finalize()),
+        "d41d8cd98f00b204e9800998ecf8427e"
+    );
+
+    let mut md5 = Md5::new();
+    md5.update(b"1234567890123456789012345678901234567890");
+    md5.update(b"1234567890123456789012345678901234567890");
+    assert_eq!(to_hex(&md5.finalize()), "57edf4a22be3c955ac49da2e2107b67a");
+}
@@ -0,0 +1,299 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use ndsutils::error::NdsError;
+use ndsutils::filesystem::{
+    delete_file, read_entry, replace_file_in_place, FATEntry, NitroFAT, NitroFNT,
+};
+use ndsutils::header::NDSCartridgeHeader;
+
+// (parent index, file names, subdirectories as (name, index))
+type Dir<'a> = (usize, &'a [&'a str], &'a [(&'a str, usize)]);
+
+// Serialises a File Name Table, numbering files consecutively in directory order.
+fn build_fnt(dirs: &[Dir]) -> Vec<u8> {
+    let mut subtables: Vec<Vec<u8>> = vec![];
+    for (_, files, subdirs) in dirs {
+        let mut st = vec![];
+        for f in *files {
+            st.push(f.len() as u8);
+            st.extend_from_slice(f.as_bytes());
+        }
+        for (d, idx) in *subdirs {
+            st.push(0x80 | d.len() as u8);
+            st.extend_from_slice(d.as_bytes());
+            st.extend_from_slice(&(0xF000 + *idx as u16).to_le_bytes());
+        }
+        st.push(0);
+        subtables.push(st);
+    }
+
+    let mut fnt = vec![];
+    let mut offset = dirs.len() * 8;
+    let mut file_id = 0u16;
+    for (i, (parent, files, _)) in dirs.iter().enumerate() {
+        fnt.extend_from_slice(&(offset as u32).to_le_bytes());
+        fnt.extend_from_slice(&file_id.to_le_bytes());
+        let parent = if i == 0 {
+            dirs.len() as u16
+        } else {
+            0xF000 + *parent as u16
+        };
+        fnt.extend_from_slice(&parent.to_le_bytes());
+        offset += subtables[i].len();
+        file_id += files.len() as u16;
+    }
+    for st in subtables {
+        fnt.extend(st);
+    }
+    fnt
+}
+
+fn parse(fnt: &[u8]) -> NitroFNT {
+    let mut rom = vec![0u8; 0x200];
+    rom.extend_from_slice(fnt);
+    let hdr = NDSCartridgeHeader {
+        fnt_offset: 0x200,
+        fnt_size: fnt.len() as u32,
+        ..Default::default()
+    };
+    NitroFNT::parse(&mut Cursor::new(&rom), &hdr).unwrap()
+}
+
+#[test]
+fn fnt_resolves_nested_paths() {
+    let fnt = parse(&build_fnt(&[
+        (0, &["a.bin"], &[("data", 1)]),
+        (0, &["b.bin"], &[("sub", 2)]),
+        (1, &["c.bin"], &[]),
+    ]));
+
+    let files: Vec<(PathBuf, u16)> = fnt.iter().collect();
+    assert_eq!(
+        files,
+        vec![
+            (PathBuf::from("a.bin"), 0),
+            (PathBuf::from("data/b.bin"), 1),
+            (PathBuf::from("data/sub/c.bin"), 2),
+        ]
+    );
+
+    assert_eq!(fnt.find_by_path(Path::new("data/sub/c.bin")), Some(2));
+    assert_eq!(fnt.find_by_path(Path::new("/a.bin")), Some(0));
+    assert_eq!(fnt.find_by_path(Path::new("data/a.bin")), None);
+    assert_eq!(fnt.find_by_path(Path::new("data/sub")), None);
+    assert_eq!(fnt.find_by_path(Path::new("")), None);
+}
+
+/* Six levels below the root, as deep as commercial ROMs go, with the directories numbered out
+of tree order so that every path has to be resolved through the parent pointers. */
+#[test]
+fn fnt_resolves_six_levels_of_nesting() {
+    let fnt = parse(&build_fnt(&[
+        (0, &["root.bin"], &[("l1", 6)]),
+        (3, &["l4.bin"], &[("l5", 5)]),
+        (4, &[], &[("l3", 3)]),
+        (2, &[], &[("l4", 1)]),
+        (6, &["l2.bin"], &[("l2", 2)]),
+        (1, &[], &[("l6", 7)]),
+        (0, &[], &[("l2", 4)]),
+        (5, &["l6a.bin", "l6b.bin"], &[]),
+    ]));
+
+    let mut files: Vec<(PathBuf, u16)> = fnt.iter().collect();
+    files.sort();
+    assert_eq!(
+        files,
+        vec![
+            (PathBuf::from("l1/l2/l2/l3/l4/l4.bin"), 1),
+            (PathBuf::from("l1/l2/l2/l3/l4/l5/l6/l6a.bin"), 3),
+            (PathBuf::from("l1/l2/l2/l3/l4/l5/l6/l6b.bin"), 4),
+            (PathBuf::from("l1/l2/l2.bin"), 2),
+            (PathBuf::from("root.bin"), 0),
+        ]
+    );
+    assert_eq!(
+        fnt.find_by_path(Path::new("l1/l2/l2/l3/l4/l5/l6/l6b.bin")),
+        Some(4)
+    );
+
+    // The same tree survives a rebuild from its paths
+    let paths: Vec<String> = files
+        .iter()
+        .map(|(p, _)| p.to_string_lossy().into_owned())
+        .collect();
+    let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+    let rebuilt = parse(&NitroFNT::from_paths(&paths, 0).unwrap().to_bytes());
+    let mut rebuilt_paths: Vec<PathBuf> = rebuilt.iter().map(|(p, _)| p).collect();
+    rebuilt_paths.sort();
+    assert_eq!(
+        rebuilt_paths,
+        files.into_iter().map(|(p, _)| p).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn fat_entries_locate_file_data() {
+    let mut rom = vec![0u8; 0x300];
+    rom[0x200..0x208].copy_from_slice(&[0x80, 0x02, 0, 0, 0x84, 0x02, 0, 0]);
+    rom[0x208..0x210].copy_from_slice(&[0x84, 0x02, 0, 0, 0x84, 0x02, 0, 0]);
+    rom[0x280..0x284].copy_from_slice(b"NTRF");
+    let hdr = NDSCartridgeHeader {
+        fat_offset: 0x200,
+        fat_size: 0x10,
+        ..Default::default()
+    };
+
+    let fat = NitroFAT::parse(&mut Cursor::new(&rom), &hdr).unwrap();
+    assert_eq!(fat.0.len(), 2);
+    assert!(fat.0[1].is_empty());
+    assert_eq!(
+        read_entry(&mut Cursor::new(&rom), &fat.0[0]).unwrap(),
+        b"NTRF"
+    );
+    assert_eq!(
+        fat.read_file_by_id(0, &mut Cursor::new(&rom)).unwrap(),
+        b"NTRF"
+    );
+    assert!(fat.read_file_by_id(2, &mut Cursor::new(&rom)).is_err());
+}
+
+#[test]
+fn fat_with_64_bit_offsets_reads_the_same_way() {
+    let mut rom = vec![0u8; 0x300];
+    rom[0x280..0x284].copy_from_slice(b"DSi!");
+    let fat: NitroFAT<u64> = NitroFAT(vec![
+        FATEntry {
+            start: 0x280,
+            end: 0x284,
+        },
+        FATEntry {
+            start: 0x1_0000_0000,
+            end: 0x1_0000_0000,
+        },
+    ]);
+
+    assert_eq!(fat.0[0].len(), 4u64);
+    assert!(fat.0[1].is_empty());
+    assert_eq!(
+        fat.read_file_by_id(0, &mut Cursor::new(&rom)).unwrap(),
+        b"DSi!"
+    );
+
+    let mut rw = Cursor::new(rom);
+    replace_file_in_place(&mut rw, &fat, 0, b"NDS").unwrap();
+    assert_eq!(read_entry(&mut rw, &fat.0[0]).unwrap(), b"NDS\0");
+}
+
+#[test]
+fn replace_file_in_place_keeps_the_slot() {
+    let mut rom = vec![0xEEu8; 0x300];
+    rom[0x200..0x208].copy_from_slice(&[0x80, 0x02, 0, 0, 0x88, 0x02, 0, 0]);
+    let hdr = NDSCartridgeHeader {
+        fat_offset: 0x200,
+        fat_size: 0x8,
+        ..Default::default()
+    };
+    let fat = NitroFAT::parse(&mut Cursor::new(&rom), &hdr).unwrap();
+
+    let mut rw = Cursor::new(rom);
+    replace_file_in_place(&mut rw, &fat, 0, b"abc").unwrap();
+    assert_eq!(rw.get_ref()[0x280..0x289], *b"abc\0\0\0\0\0\xEE");
+
+    assert!(matches!(
+        replace_file_in_place(&mut rw, &fat, 0, &[1; 9]),
+        Err(NdsError::FileTooLarge {
+            expected: 8,
+            got: 9
+        })
+    ));
+}
+
+#[test]
+fn delete_file_leaves_an_unused_fat_entry() {
+    let mut fnt = NitroFNT::from_paths(&["a.bin", "d/x.bin", "d/y.bin", "d/z.bin"], 0).unwrap();
+    let mut fat = NitroFAT(
+        (1..=4)
+            .map(|n| FATEntry {
+                start: n * 0x200,
+                end: n * 0x200 + 4,
+            })
+            .collect(),
+    );
+    let spans =
+        |fat: &NitroFAT| -> Vec<(u32, u32)> { fat.0.iter().map(|e| (e.start, e.end)).collect() };
+
+    // The first file of a directory goes without moving anything
+    delete_file(&mut fat, &mut fnt, 0).unwrap();
+    assert_eq!(fnt.find_by_path(Path::new("a.bin")), None);
+    assert_eq!(
+        spans(&fat),
+        vec![(0, 0), (0x400, 0x404), (0x600, 0x604), (0x800, 0x804)]
+    );
+
+    // A file between two others stays, as an empty file, so that none of them moves
+    delete_file(&mut fat, &mut fnt, 2).unwrap();
+    let ids: Vec<(PathBuf, u16)> = fnt.iter().collect();
+    assert_eq!(
+        ids,
+        vec![
+            (PathBuf::from("d/x.bin"), 1),
+            (PathBuf::from("d/y.bin"), 2),
+            (PathBuf::from("d/z.bin"), 3)
+        ]
+    );
+    assert_eq!(
+        spans(&fat),
+        vec![(0, 0), (0x400, 0x404), (0, 0), (0x800, 0x804)]
+    );
+
+    // The last file of a directory goes without moving anything
+    delete_file(&mut fat, &mut fnt, 3).unwrap();
+    assert_eq!(fnt.find_by_path(Path::new("d/z.bin")), None);
+    assert_eq!(fnt.find_by_path(Path::new("d/x.bin")), Some(1));
+    assert_eq!(spans(&fat), vec![(0, 0), (0x400, 0x404), (0, 0), (0, 0)]);
+
+    assert!(delete_file(&mut fat, &mut fnt, 3).is_err());
+    assert!(delete_file(&mut fat, &mut fnt, 4).is_err());
+}
+
+#[test]
+fn fnt_listing_round_trips() {
+    let raw = build_fnt(&[
+        (0, &["r.bin"], &[("a", 1), ("b", 2)]),
+        (0, &["y.bin", "x.bin"], &[]),
+        (0, &["z.bin"], &[]),
+    ]);
+    let fnt = parse(&raw);
+
+    let mut listing = vec![];
+    fnt.write_listing(&mut listing).unwrap();
+    assert_eq!(
+        String::from_utf8(listing.clone()).unwrap(),
+        "0000  r.bin\n0001  a/y.bin\n0002  a/x.bin\n0003  b/z.bin\n"
+    );
+    let read_back = NitroFNT::from_listing(&mut &listing[..]).unwrap();
+    assert_eq!(read_back.to_bytes(), raw);
+
+    // Root files needn't come first
+    let listing = "0005  d/e/x.bin\n0006  d/e/y.bin\n0007  r.bin\n";
+    let fnt = NitroFNT::from_listing(&mut listing.as_bytes()).unwrap();
+    assert_eq!(fnt.find_by_path(Path::new("r.bin")), Some(7));
+    assert_eq!(fnt.find_by_path(Path::new("d/e/y.bin")), Some(6));
+    let mut out = vec![];
+    fnt.write_listing(&mut out).unwrap();
+    assert_eq!(out, listing.as_bytes());
+
+    for bad in [
+        "0000  a/x.bin\n0001  b.bin\n0002  a/y.bin\n",
+        "0000  x.bin\n0000  y.bin\n",
+        "zzzz  x.bin\n",
+        "0000  a//x.bin\n",
+    ] {
+        assert!(
+            NitroFNT::from_listing(&mut bad.as_bytes()).is_err(),
+            "{:?}",
+            bad
+        );
+    }
+}
@@ -0,0 +1,378 @@
+use std::io::Cursor;
+use std::mem::size_of;
+
+use ndsutils::crc::bios_get_crc16;
+use ndsutils::error::NdsError;
+use ndsutils::header::{
+    dump_header_hex, DSiExtendedHeader, NDSCartridgeHeader, NdsRegion, UnitCode, LOGO_CRC16,
+};
+
+#[test]
+fn header_is_0x180_bytes() {
+    assert_eq!(size_of::<NDSCartridgeHeader>(), 0x180);
+}
+
+#[test]
+fn parse_nds_reads_fields_at_gbatek_offsets() {
+    let mut rom = vec![0u8; 0x200];
+    rom[0x00..0x0C].copy_from_slice(b"POKEMON D\0\0\0");
+    rom[0x0C..0x10].copy_from_slice(b"ADAE");
+    rom[0x20..0x24].copy_from_slice(&0x4000u32.to_le_bytes());
+    rom[0x2C..0x30].copy_from_slice(&0x1234u32.to_le_bytes());
+    rom[0x68..0x6C].copy_from_slice(&0xAB000u32.to_le_bytes());
+    rom[0x15E..0x160].copy_from_slice(&0xBEEFu16.to_le_bytes());
+
+    let hdr = NDSCartridgeHeader::parse_nds(Cursor::new(&rom)).unwrap();
+
+    assert_eq!(&hdr.gametitle, b"POKEMON D\0\0\0");
+    assert_eq!({ hdr.gamecode }, u32::from_le_bytes(*b"ADAE"));
+    assert_eq!({ hdr.arm9off }, 0x4000);
+    assert_eq!({ hdr.arm9size }, 0x1234);
+    assert_eq!({ hdr.banner_offset }, 0xAB000);
+    assert_eq!({ hdr.header_crc }, 0xBEEF);
+}
+
+#[test]
+fn header_crc16_covers_first_0x15e_bytes() {
+    let mut hdr = NDSCartridgeHeader {
+        gamecode: u32::from_le_bytes(*b"ADAE"),
+        ..Default::default()
+    };
+    assert!(!hdr.validate_header_crc16());
+
+    hdr.header_crc = hdr.compute_header_crc16();
+    assert!(hdr.validate_header_crc16());
+
+    // Fields after the CRC aren't covered
+    hdr.debug_rom_offset = 0x1000;
+    assert!(hdr.validate_header_crc16());
+}
+
+#[test]
+fn write_to_updates_both_crcs() {
+    let mut rom = vec![0u8; 0x8000];
+    rom[0x4000..0x4008].copy_from_slice(b"encryObj");
+    let mut w = Cursor::new(rom);
+
+    let mut hdr = NDSCartridgeHeader {
+        arm9off: 0x4000,
+        ..Default::default()
+    };
+    hdr.write_to(&mut w).unwrap();
+
+    let expected_secure_area_crc = bios_get_crc16(&w.get_ref()[0x4000..0x8000]);
+    assert_eq!({ hdr.secure_area_crc }, expected_secure_area_crc);
+    assert!(hdr.validate_header_crc16());
+
+    let reread = NDSCartridgeHeader::parse_nds(&mut w).unwrap();
+    assert_eq!({ reread.secure_area_crc }, expected_secure_area_crc);
+    assert!(reread.validate_header_crc16());
+}
+
+#[test]
+fn game_title_and_maker_code_strip_padding() {
+    let mut rom = vec![0u8; 0x200];
+    rom[0x00..0x0C].copy_from_slice(b"POKEMON D\0\0\0");
+    rom[0x10..0x12].copy_from_slice(b"01");
+
+    let hdr = NDSCartridgeHeader::parse_nds(Cursor::new(&rom)).unwrap();
+    assert_eq!(hdr.game_title(), "POKEMON D");
+    assert_eq!(hdr.maker_code_str(), "01");
+}
+
+#[test]
+fn unprintable_maker_codes_are_shown_as_question_marks() {
+    for (code, shown) in [(*b"8P", "8P"), ([0, 0], "??"), ([b'0', 0xFF], "??")] {
+        let mut rom = vec![0u8; 0x200];
+        rom[0x10..0x12].copy_from_slice(&code);
+        let hdr = NDSCartridgeHeader::parse_nds(Cursor::new(&rom)).unwrap();
+        assert_eq!(hdr.maker_code_str(), shown);
+    }
+}
+
+#[test]
+fn game_code_is_shown_in_document_order() {
+    let hdr = NDSCartridgeHeader {
+        gamecode: u32::from_le_bytes(*b"IPKE"),
+        ..Default::default()
+    };
+
+    assert_eq!(&hdr.game_code_str(), b"IPKE");
+    assert_eq!(hdr.game_code_display(), "IPKE");
+}
+
+#[test]
+fn dsi_extension_depends_on_unit_code() {
+    let mut rom = vec![0u8; 0x400];
+    rom[0x1C0..0x1C4].copy_from_slice(&0x0080_0000u32.to_le_bytes());
+    rom[0x220..0x224].copy_from_slice(&0x4000u32.to_le_bytes());
+    rom[0x230..0x234].copy_from_slice(b"EKPI");
+    assert_eq!(size_of::<DSiExtendedHeader>(), 0x400 - 0x180);
+
+    let hdr = NDSCartridgeHeader::parse_nds(Cursor::new(&rom)).unwrap();
+    assert_eq!(hdr.unit_code(), UnitCode::Nds);
+    assert!(hdr.parse_dsi_extension(&mut Cursor::new(&rom)).is_none());
+
+    rom[0x12] = 0x03;
+    let hdr = NDSCartridgeHeader::parse_nds(Cursor::new(&rom)).unwrap();
+    assert_eq!(hdr.unit_code(), UnitCode::DsiOnly);
+    let ext = hdr.parse_dsi_extension(&mut Cursor::new(&rom)).unwrap();
+    assert_eq!({ ext.arm9i_off }, 0x0080_0000);
+    assert_eq!({ ext.modcrypt1_offset }, 0x4000);
+    assert_eq!(&ext.title_id_gamecode, b"EKPI");
+}
+
+#[test]
+fn unit_code_keeps_unknown_values() {
+    assert_eq!(UnitCode::from(0x02), UnitCode::NdsDsi);
+    assert_eq!(UnitCode::from(0x01), UnitCode::Unknown(0x01));
+    assert_eq!(UnitCode::NdsDsi.to_string(), "NDS+DSi");
+}
+
+#[test]
+fn dsi_compatibility_follows_unit_code() {
+    for (code, dsi) in [(0x00, false), (0x01, false), (0x02, true), (0x03, true)] {
+        let hdr = NDSCartridgeHeader {
+            unitcode: [code],
+            ..Default::default()
+        };
+        assert_eq!(hdr.is_dsi_compatible(), dsi, "unit code {:#04x}", code);
+        assert_eq!(hdr.is_nds_only(), !dsi, "unit code {:#04x}", code);
+    }
+}
+
+#[test]
+fn autostart_only_touches_bit_2() {
+    let mut hdr = NDSCartridgeHeader {
+        autostart: [0x01],
+        ..Default::default()
+    };
+    assert!(!hdr.autostart_enabled());
+
+    hdr.set_autostart(true);
+    assert!(hdr.autostart_enabled());
+    assert_eq!(hdr.autostart, [0x05]);
+
+    hdr.set_autostart(false);
+    assert_eq!(hdr.autostart, [0x01]);
+}
+
+#[test]
+fn header_round_trips_through_write_to_and_parse_nds() {
+    let mut hdr = NDSCartridgeHeader {
+        gametitle: *b"POKEMON D\0\0\0",
+        gamecode: u32::from_le_bytes(*b"ADAE"),
+        makercode: u16::from_le_bytes(*b"01"),
+        arm9off: 0x4000,
+        arm9size: 0x800,
+        arm7off: 0x8000,
+        banner_offset: 0x9000,
+        ..Default::default()
+    };
+    let mut w = Cursor::new(vec![0u8; 0x8000]);
+    hdr.write_to(&mut w).unwrap();
+
+    let reread = NDSCartridgeHeader::parse_nds(&mut w).unwrap();
+    assert_eq!(reread, hdr);
+    assert_ne!(reread, NDSCartridgeHeader::default());
+}
+
+#[test]
+fn region_comes_from_region_byte_then_gamecode() {
+    let mut hdr = NDSCartridgeHeader {
+        gamecode: u32::from_le_bytes(*b"ADAE"),
+        ..Default::default()
+    };
+    assert_eq!(hdr.region(), NdsRegion::Usa);
+
+    hdr.gamecode = u32::from_le_bytes(*b"ADAA");
+    assert_eq!(hdr.region(), NdsRegion::World);
+
+    hdr.ndsregion = [0x80];
+    assert_eq!(hdr.region(), NdsRegion::China);
+    assert_eq!(NdsRegion::from(0x01), NdsRegion::Unknown(0x01));
+}
+
+#[test]
+fn rom_capacity_is_128k_shifted() {
+    let mut hdr = NDSCartridgeHeader::default();
+    assert_eq!(hdr.rom_capacity_bytes(), 0x20000);
+    hdr.devicecaps = [0x09];
+    assert_eq!(hdr.rom_capacity_bytes(), 64 * 1024 * 1024);
+    hdr.devicecaps = [0xFF];
+    assert_eq!(hdr.rom_capacity_bytes(), u64::MAX);
+}
+
+#[test]
+fn entry_and_ram_addresses_read_unaligned_fields() {
+    let mut rom = vec![0u8; 0x200];
+    rom[0x24..0x28].copy_from_slice(&0x0200_0800u32.to_le_bytes());
+    rom[0x28..0x2C].copy_from_slice(&0x0200_0000u32.to_le_bytes());
+    rom[0x34..0x38].copy_from_slice(&0x0238_0000u32.to_le_bytes());
+    rom[0x38..0x3C].copy_from_slice(&0x037F_8000u32.to_le_bytes());
+
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+    assert_eq!(hdr.arm9_entry_address(), 0x0200_0800);
+    assert_eq!(hdr.arm9_ram_address(), 0x0200_0000);
+    assert_eq!(hdr.arm7_entry_address(), 0x0238_0000);
+    assert_eq!(hdr.arm7_ram_address(), 0x037F_8000);
+}
+
+#[test]
+fn dump_header_hex_matches_xxd() {
+    let mut rom = vec![0u8; 0x180];
+    rom[0x00..0x0C].copy_from_slice(b"POKEMON D\0\0\0");
+    rom[0x0C..0x10].copy_from_slice(b"ADAE");
+    rom[0x10..0x12].copy_from_slice(b"01");
+    rom[0x20..0x24].copy_from_slice(&0x4000u32.to_le_bytes());
+    rom[0x28..0x2C].copy_from_slice(&0x2000000u32.to_le_bytes());
+    rom[0x15E..0x160].copy_from_slice(&0xBEEFu16.to_le_bytes());
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+
+    // Produced by `xxd` over the same 180h bytes
+    let golden = concat!(
+        "00000000: 504f 4b45 4d4f 4e20 4400 0000 4144 4145  POKEMON D...ADAE\n",
+        "00000010: 3031 0000 0000 0000 0000 0000 0000 0000  01..............\n",
+        "00000020: 0040 0000 0000 0000 0000 0002 0000 0000  .@..............\n",
+        "00000030: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "00000040: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "00000050: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "00000060: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "00000070: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "00000080: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "00000090: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "000000a0: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "000000b0: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "000000c0: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "000000d0: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "000000e0: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "000000f0: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "00000100: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "00000110: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "00000120: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "00000130: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "00000140: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "00000150: 0000 0000 0000 0000 0000 0000 0000 efbe  ................\n",
+        "00000160: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+        "00000170: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n",
+    );
+    assert_eq!(dump_header_hex(&hdr), golden);
+}
+
+#[test]
+fn parse_nds_rejects_short_files() {
+    let result = NDSCartridgeHeader::parse_nds_bytes(&[0u8; 0x100]);
+
+    assert!(matches!(
+        result,
+        Err(NdsError::FileTooSmall {
+            expected: 0x180,
+            got: 0x100
+        })
+    ));
+}
+
+#[test]
+fn secure_area_disable_follows_078h() {
+    let mut rom = vec![0u8; 0x200];
+    assert!(!NDSCartridgeHeader::parse_nds_bytes(&rom)
+        .unwrap()
+        .secure_area_disable());
+
+    rom[0x78..0x80].copy_from_slice(&[0x13, 0x37, 0, 0, 0, 0, 0, 1]);
+    assert!(NDSCartridgeHeader::parse_nds_bytes(&rom)
+        .unwrap()
+        .secure_area_disable());
+}
+
+#[test]
+fn is_valid_magic_needs_the_logo_and_its_crc() {
+    let mut hdr = NDSCartridgeHeader::default();
+    assert!(!hdr.is_valid_magic());
+
+    // A stored CRC of CF56h alone isn't enough if the logo doesn't hash to it
+    hdr.logo_crc = LOGO_CRC16;
+    assert!(!hdr.is_valid_magic());
+
+    hdr.logo = [0xFF; 0x9C];
+    hdr.logo_crc = bios_get_crc16(&hdr.logo);
+    assert!(!hdr.is_valid_magic());
+}
+
+#[cfg(feature = "rom-db")]
+#[test]
+fn rom_db_finds_known_codes() {
+    use ndsutils::romdb::lookup_rom_db;
+
+    let entry = lookup_rom_db(u32::from_le_bytes(*b"ASME"), u16::from_le_bytes(*b"01")).unwrap();
+    assert_eq!(entry.name, "Super Mario 64 DS");
+    assert_eq!(entry.region, NdsRegion::Usa);
+    assert_eq!(entry.revision, 0);
+
+    assert_eq!(
+        lookup_rom_db(u32::from_le_bytes(*b"ASME"), u16::from_le_bytes(*b"08")),
+        None
+    );
+}
+
+#[test]
+fn header_displays_as_a_table() {
+    let mut rom = vec![0u8; 0x200];
+    rom[..8].copy_from_slice(b"TESTGAME");
+    rom[0x0C..0x12].copy_from_slice(b"ATSE01");
+    rom[0x2C..0x30].copy_from_slice(&0x800u32.to_le_bytes());
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+
+    let table = hdr.to_string();
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 40);
+    assert_eq!(
+        lines[0],
+        "000h  Game title               54 45 53 54 47 41 4D 45 00 00 00 00 TESTGAME"
+    );
+    assert!(table.contains("\n00Ch  Game code                45535441"));
+    assert!(table.contains(
+        "\n02Ch  ARM9 size                00000800                            2048 bytes\n"
+    ));
+    assert!(table.contains("\n01Fh  Autostart                00\n"));
+}
+
+#[test]
+fn set_region_rewrites_the_region_byte_and_gamecode_letter() {
+    let mut hdr = NDSCartridgeHeader {
+        gamecode: u32::from_le_bytes(*b"ADAJ"),
+        ..Default::default()
+    };
+
+    hdr.set_region(NdsRegion::Korea).unwrap();
+    assert_eq!(&hdr.game_code_str(), b"ADAK");
+    assert_eq!(hdr.ndsregion, [0x40]);
+    assert_eq!(hdr.region(), NdsRegion::Korea);
+
+    hdr.set_region(NdsRegion::Europe).unwrap();
+    assert_eq!(&hdr.game_code_str(), b"ADAP");
+    assert_eq!(hdr.ndsregion, [0x00]);
+    assert_eq!(hdr.region(), NdsRegion::Europe);
+
+    assert!(hdr.set_region(NdsRegion::World).is_err());
+    assert_eq!(&hdr.game_code_str(), b"ADAP");
+}
+
+#[test]
+fn header_converts_from_an_in_memory_image() {
+    let mut rom = vec![0u8; 0x300];
+    rom[..8].copy_from_slice(b"TESTGAME");
+    rom[0x20..0x24].copy_from_slice(&0x4000u32.to_le_bytes());
+
+    let hdr = NDSCartridgeHeader::try_from(&rom[..]).unwrap();
+    assert_eq!(hdr, NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap());
+    assert_eq!(hdr.game_title(), "TESTGAME");
+    assert_eq!({ hdr.arm9off }, 0x4000);
+
+    let short = &rom[..size_of::<NDSCartridgeHeader>() - 1];
+    assert!(matches!(
+        NDSCartridgeHeader::try_from(short),
+        Err(NdsError::BufferTooSmall { expected, got })
+            if expected == size_of::<NDSCartridgeHeader>() && got == expected - 1
+    ));
+}
@@ -0,0 +1,66 @@
+use std::io::Cursor;
+
+use ndsutils::filesystem::NitroFAT;
+use ndsutils::header::NDSCartridgeHeader;
+use ndsutils::overlay::{extract_overlay, overlay_summary, OverlayTable};
+
+#[test]
+fn overlay_tables_parse_entries() {
+    let mut rom = vec![0u8; 0x400];
+    for (i, field) in [3u32, 0x0210_0000, 0x1000, 0x20, 0, 0, 7, 0]
+        .iter()
+        .enumerate()
+    {
+        rom[0x200 + i * 4..0x204 + i * 4].copy_from_slice(&field.to_le_bytes());
+    }
+    let hdr = NDSCartridgeHeader {
+        arm9_overlay_offset: 0x200,
+        arm9_overlay_size: 0x20,
+        ..Default::default()
+    };
+
+    let arm9 = OverlayTable::parse_arm9(&mut Cursor::new(&rom), &hdr).unwrap();
+    let entry = arm9.iter().next().unwrap();
+    assert_eq!(arm9.iter().count(), 1);
+    assert_eq!(entry.overlay_id, 3);
+    assert_eq!(entry.ram_address, 0x0210_0000);
+    assert_eq!(entry.file_id, 7);
+
+    let arm7 = OverlayTable::parse_arm7(&mut Cursor::new(&rom), &hdr).unwrap();
+    assert_eq!(arm7.iter().count(), 0);
+}
+
+#[test]
+fn overlays_are_extracted_through_the_fat() {
+    let mut rom = vec![0u8; 0x400];
+    for (i, field) in [0u32, 0x0200_0000, 4, 0, 0, 0, 1, 0].iter().enumerate() {
+        rom[0x200 + i * 4..0x204 + i * 4].copy_from_slice(&field.to_le_bytes());
+    }
+    for (i, field) in [0u32, 0, 0x300, 0x304].iter().enumerate() {
+        rom[0x280 + i * 4..0x284 + i * 4].copy_from_slice(&field.to_le_bytes());
+    }
+    rom[0x300..0x304].copy_from_slice(b"OVL0");
+    let hdr = NDSCartridgeHeader {
+        arm9_overlay_offset: 0x200,
+        arm9_overlay_size: 0x20,
+        fat_offset: 0x280,
+        fat_size: 0x10,
+        ..Default::default()
+    };
+
+    let mut r = Cursor::new(&rom);
+    let arm9 = OverlayTable::parse_arm9(&mut r, &hdr).unwrap();
+    let arm7 = OverlayTable::parse_arm7(&mut r, &hdr).unwrap();
+    let fat = NitroFAT::parse(&mut r, &hdr).unwrap();
+    assert_eq!(extract_overlay(&mut r, &fat, &arm9.0[0]).unwrap(), b"OVL0");
+
+    let mut missing = arm9.0[0];
+    missing.file_id = 2;
+    assert!(extract_overlay(&mut r, &fat, &missing).is_err());
+
+    assert_eq!(
+        overlay_summary(&arm9, &arm7),
+        "CPU   ID    RAM address  Size      File ID\n\
+         ARM9  0     0x02000000   0x000004  1\n"
+    );
+}
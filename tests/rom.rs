@@ -0,0 +1,148 @@
+use std::cell::Cell;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use ndsutils::builder::RomBuilder;
+use ndsutils::rom::{pad_rom, trim_rom, NdsRom};
+
+fn padded_rom(data_end: usize, size: usize) -> Vec<u8> {
+    let mut rom = vec![0xFFu8; size];
+    rom[..data_end].fill(0);
+    rom[0x68..0x6C].copy_from_slice(&0x200u32.to_le_bytes());
+    rom[0x200..0x202].copy_from_slice(&1u16.to_le_bytes());
+    rom
+}
+
+#[test]
+fn trim_rom_strips_trailing_padding() {
+    let rom = padded_rom(0xC00, 0x20000);
+    let mut out = vec![];
+
+    let written = trim_rom(&mut Cursor::new(&rom), &mut out, None).unwrap();
+    assert_eq!(written, 0xC00);
+    assert_eq!(out, rom[..0xC00]);
+}
+
+#[test]
+fn trim_rom_refuses_to_cut_into_banner() {
+    let rom = padded_rom(0x300, 0x1000);
+
+    assert!(trim_rom(&mut Cursor::new(&rom), &mut vec![], None).is_err());
+}
+
+#[test]
+fn pad_rom_fills_to_next_power_of_two() {
+    let rom = padded_rom(0xC00, 0xC00);
+    let mut out = vec![];
+
+    let written = pad_rom(&mut Cursor::new(&rom), &mut out, None, None).unwrap();
+    assert_eq!(written, 0x1000);
+    assert_eq!(out[..0xC00], rom[..]);
+    assert!(out[0xC00..].iter().all(|&b| b == 0xFF));
+}
+
+#[test]
+fn pad_rom_validates_target_size() {
+    let rom = padded_rom(0xC00, 0xC00);
+
+    assert!(pad_rom(&mut Cursor::new(&rom), &mut vec![], Some(0x3000), None).is_err());
+    assert!(pad_rom(&mut Cursor::new(&rom), &mut vec![], Some(0x800), None).is_err());
+    assert_eq!(
+        pad_rom(&mut Cursor::new(&rom), &mut vec![], Some(0x4000), None).unwrap(),
+        0x4000
+    );
+}
+
+#[test]
+fn pad_rom_reports_progress_up_to_the_target() {
+    let rom = padded_rom(0xC00, 0xC00);
+    let last = Cell::new((0, 0));
+    let calls = Cell::new(0);
+
+    let progress = |done: u64, total: u64| {
+        assert!(done >= last.get().0 && done <= total);
+        last.set((done, total));
+        calls.set(calls.get() + 1);
+    };
+    pad_rom(
+        &mut Cursor::new(&rom),
+        &mut vec![],
+        Some(0x40000),
+        Some(&progress),
+    )
+    .unwrap();
+
+    assert_eq!(last.get(), (0x40000, 0x40000));
+    assert!(calls.get() > 1);
+}
+
+fn built_rom() -> Vec<u8> {
+    let mut builder = RomBuilder::new();
+    builder
+        .set_arm9(&[9; 0x900])
+        .set_arm7(&[7; 0x70])
+        .add_file("dir/file.bin", b"contents");
+    let mut image = vec![];
+    builder.build(&mut image).unwrap();
+    image
+}
+
+#[test]
+fn nds_rom_loads_every_section() {
+    let image = built_rom();
+
+    let rom = NdsRom::load_all(&mut Cursor::new(&image)).unwrap();
+
+    assert_eq!(rom.arm9.unwrap().raw_data, vec![9; 0x900]);
+    assert_eq!(rom.arm7.unwrap().raw_data, vec![7; 0x70]);
+    assert!(rom.banner.is_none());
+    let files: Vec<_> = rom.fnt.iter().collect();
+    assert_eq!(files, vec![(PathBuf::from("dir/file.bin"), 0)]);
+    assert_eq!(rom.fat.0[0].len(), 8);
+}
+
+#[test]
+fn nds_rom_loads_bootcode_on_request() {
+    let image = built_rom();
+    let mut cursor = Cursor::new(&image);
+
+    let mut rom = NdsRom::load_header_only(&mut cursor).unwrap();
+    assert!(rom.arm9.is_none() && rom.arm7.is_none());
+    assert_eq!({ rom.header.arm9size }, 0x900);
+
+    assert_eq!(rom.load_arm9(&mut cursor).unwrap().raw_data, vec![9; 0x900]);
+    assert!(rom.arm9.is_some() && rom.arm7.is_none());
+
+    let truncated = &image[..0x4800];
+    assert!(rom.load_arm9(&mut Cursor::new(truncated)).is_err());
+}
+
+#[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+#[test]
+fn mmap_reads_like_a_file() {
+    use std::io::{Read, Seek, SeekFrom};
+
+    use ndsutils::header::NDSCartridgeHeader;
+    use ndsutils::mmap::MmapNdsRom;
+
+    let mut image = vec![];
+    RomBuilder::new()
+        .set_arm9(&[1; 0x10])
+        .set_arm7(&[2; 0x10])
+        .build(&mut image)
+        .unwrap();
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("mmap_reads_like_a_file.nds");
+    std::fs::write(&path, &image).unwrap();
+
+    let mut rom = MmapNdsRom::open(&path).unwrap();
+    assert_eq!(&rom[..], &image[..]);
+    let hdr = NDSCartridgeHeader::parse_nds(&mut rom).unwrap();
+    assert_eq!({ hdr.arm9size }, 0x10);
+
+    let mut buf = [0; 8];
+    rom.seek(SeekFrom::End(-4)).unwrap();
+    assert_eq!(rom.read(&mut buf).unwrap(), 4);
+    assert_eq!(buf[..4], image[image.len() - 4..]);
+    assert_eq!(rom.read(&mut buf).unwrap(), 0);
+    assert!(rom.seek(SeekFrom::Current(-0x10_0000)).is_err());
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use std::io::Cursor;
+
+use ndsutils::builder::RomBuilder;
+use ndsutils::header::NDSCartridgeHeader;
+use ndsutils::validate::{check_section_overlap, validate_rom, SectionOverlapWarning, LOGO_CRC16};
+
+#[test]
+fn overlapping_sections_are_reported() {
+    let hdr = NDSCartridgeHeader {
+        arm9off: 0x4000,
+        arm9size: 0x1000,
+        arm7off: 0x5000,
+        arm7size: 0x800,
+        fnt_offset: 0x4F00,
+        fnt_size: 0x200,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        check_section_overlap(&hdr, 1),
+        vec![
+            SectionOverlapWarning {
+                first: "ARM9",
+                second: "FNT",
+                start: 0x4F00,
+                end: 0x5000,
+            },
+            SectionOverlapWarning {
+                first: "ARM7",
+                second: "FNT",
+                start: 0x5000,
+                end: 0x5100,
+            },
+        ]
+    );
+    assert_eq!(
+        check_section_overlap(&hdr, 1)[0].to_string(),
+        "ARM9 and FNT overlap at 0x4f00..0x5000"
+    );
+}
+
+#[test]
+fn built_roms_have_no_overlapping_sections() {
+    let mut image = vec![];
+    RomBuilder::new()
+        .set_arm9(&[0; 0x300])
+        .set_arm7(&[0; 0x300])
+        .add_file("a.bin", b"a")
+        .build(&mut image)
+        .unwrap();
+
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&image).unwrap();
+    assert_eq!(check_section_overlap(&hdr, 1), vec![]);
+
+    let checks = validate_rom(&mut Cursor::new(&image)).unwrap();
+    let overlap = checks.iter().find(|c| c.name == "Section overlap").unwrap();
+    assert!(overlap.passed);
+}
+
+#[test]
+fn minimal_rom_passes_everything_but_the_logo() {
+    let rom = common::create_minimal_test_rom();
+    let checks = validate_rom(&mut Cursor::new(&rom)).unwrap();
+
+    let failed: Vec<&str> = checks
+        .iter()
+        .filter(|c| !c.passed)
+        .map(|c| c.name.as_str())
+        .collect();
+    assert_eq!(failed, ["Logo CRC16"]);
+    assert!(checks.iter().any(|c| c.name == "Secure area CRC16"));
+}
+
+#[test]
+fn damaged_logo_fails_even_with_the_right_crc_field() {
+    let mut rom = common::create_minimal_test_rom();
+    rom[0x15C..0x15E].copy_from_slice(&LOGO_CRC16.to_le_bytes());
+    let checks = validate_rom(&mut Cursor::new(&rom)).unwrap();
+
+    let logo = checks.iter().find(|c| c.name == "Logo CRC16").unwrap();
+    assert!(!logo.passed);
+    assert_eq!(logo.expected, "0xcf56");
+    assert!(logo.actual.ends_with("(stored 0xcf56)"));
+}
+
+#[test]
+fn overlap_checks_use_the_banner_versions_size() {
+    let hdr = NDSCartridgeHeader {
+        banner_offset: 0x8000,
+        fat_offset: 0x8900,
+        fat_size: 0x10,
+        ..Default::default()
+    };
+    assert_eq!(check_section_overlap(&hdr, 1), vec![]);
+    assert_eq!(
+        check_section_overlap(&hdr, 3),
+        vec![SectionOverlapWarning {
+            first: "FAT",
+            second: "banner",
+            start: 0x8900,
+            end: 0x8910,
+        }]
+    );
+}
+
+#[test]
+fn unparseable_banner_fails_the_banner_check() {
+    let mut rom = vec![];
+    RomBuilder::new()
+        .set_arm9(&[0; 0x800])
+        .set_arm7(&[0x1E, 0xFF, 0x2F, 0xE1])
+        .set_banner(&[1; 0x840])
+        .build(&mut rom)
+        .unwrap();
+    let hdr = NDSCartridgeHeader::parse_nds_bytes(&rom).unwrap();
+    let banner_offset = hdr.banner_offset as usize;
+
+    let checks = validate_rom(&mut Cursor::new(&rom)).unwrap();
+    let banner = checks.iter().find(|c| c.name == "Banner CRC16").unwrap();
+    assert!(banner.passed);
+
+    // An unknown banner version
+    rom[banner_offset..banner_offset + 2].copy_from_slice(&0x0042u16.to_le_bytes());
+    let checks = validate_rom(&mut Cursor::new(&rom)).unwrap();
+    let banner = checks.iter().find(|c| c.name == "Banner CRC16").unwrap();
+    assert!(!banner.passed);
+    assert_eq!(banner.expected, "a readable banner");
+}